@@ -0,0 +1,170 @@
+// Which audio tracks go into the export, how loud each one is, and whether they're mixed into one.
+//
+// Recordings from OBS and the like often have the game, mic and voice chat on separate tracks. Left to
+// itself ffmpeg only keeps one of them, so the export always maps the tracks picked here explicitly.
+
+use eframe::egui;
+
+use crate::probe::AudioStream;
+
+// Integrated loudness `loudnorm` aims for, in LUFS. This is the EBU R128 target.
+pub const LOUDNESS_TARGET: f32 = -23.0;
+
+#[derive(Clone, PartialEq)]
+pub struct TrackSettings {
+    pub include: bool,
+    pub mute: bool,
+    // 1.0 leaves the track as it is.
+    pub volume: f32,
+}
+
+impl Default for TrackSettings {
+    fn default() -> Self {
+        Self {
+            include: true,
+            mute: false,
+            volume: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct AudioSettings {
+    // One per audio stream of the video, in stream order.
+    pub tracks: Vec<TrackSettings>,
+    pub mix_down: bool,
+    pub normalize: bool,
+}
+
+// What the export does with the audio, in terms of `0:a:N` stream numbers.
+#[derive(Clone, PartialEq)]
+pub struct AudioPlan {
+    // (stream number, volume)
+    pub tracks: Vec<(usize, f32)>,
+    pub mix_down: bool,
+    pub normalize: bool,
+}
+
+impl AudioPlan {
+    // Copying the audio only works if none of it is touched.
+    pub fn needs_reencode(&self) -> bool {
+        self.normalize || (self.mix_down && self.tracks.len() > 1) || self.tracks.iter().any(|(_, volume)| *volume != 1.0)
+    }
+}
+
+impl AudioSettings {
+    pub fn for_streams(count: usize) -> Self {
+        Self {
+            tracks: vec![TrackSettings::default(); count],
+            ..Default::default()
+        }
+    }
+
+    pub fn plan(&self) -> AudioPlan {
+        let tracks = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| track.include)
+            .map(|(stream, track)| (stream, if track.mute { 0.0 } else { track.volume }))
+            .collect();
+        AudioPlan {
+            tracks,
+            mix_down: self.mix_down,
+            normalize: self.normalize,
+        }
+    }
+
+    // Short description for the options grid, e.g. "2 of 3 tracks, mixed".
+    pub fn summary(&self) -> String {
+        if self.tracks.is_empty() {
+            return "No audio".to_owned();
+        }
+        let included = self.tracks.iter().filter(|t| t.include).count();
+        let mut summary = if included == self.tracks.len() {
+            format!("All {} tracks", self.tracks.len())
+        } else {
+            format!("{included} of {} tracks", self.tracks.len())
+        };
+        if self.mix_down && included > 1 {
+            summary.push_str(", mixed");
+        }
+        if self.normalize {
+            summary.push_str(", normalized");
+        }
+        summary
+    }
+}
+
+pub fn audio_settings_ui(ui: &mut egui::Ui, streams: &[AudioStream], settings: &mut AudioSettings) {
+    if streams.is_empty() {
+        ui.label("This video has no audio.");
+        return;
+    }
+    egui::Grid::new("audio_tracks").num_columns(4).striped(true).show(ui, |ui| {
+        for (number, (stream, track)) in streams.iter().zip(&mut settings.tracks).enumerate() {
+            ui.checkbox(&mut track.include, "").on_hover_text("Include this track in the export");
+            ui.add(egui::Label::new(stream.label(number + 1)).truncate(true));
+            ui.add_enabled_ui(track.include, |ui| {
+                ui.checkbox(&mut track.mute, "Mute");
+            });
+            ui.add_enabled(
+                track.include && !track.mute,
+                egui::Slider::new(&mut track.volume, 0.0..=2.0)
+                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0))
+                    .custom_parser(|s| s.trim().trim_end_matches('%').parse::<f64>().ok().map(|v| v / 100.0)),
+            );
+            ui.end_row();
+        }
+    });
+    ui.separator();
+    let included = settings.tracks.iter().filter(|t| t.include).count();
+    ui.add_enabled(included > 1, egui::Checkbox::new(&mut settings.mix_down, "Mix selected tracks into one"))
+        .on_hover_text("Otherwise every selected track is kept as its own track");
+    ui.checkbox(&mut settings.normalize, format!("Normalize loudness ({LOUDNESS_TARGET} LUFS)"))
+        .on_hover_text("Runs EBU R128 loudness normalization (loudnorm) on the audio");
+    if settings.plan().needs_reencode() {
+        ui.label("The audio will be re-encoded to AAC.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_from_settings() {
+        let mut settings = AudioSettings::for_streams(3);
+        assert!(settings.plan().tracks == [(0, 1.0), (1, 1.0), (2, 1.0)]);
+        settings.tracks[0].mute = true;
+        settings.tracks[1].include = false;
+        settings.tracks[2].volume = 1.5;
+        // Muted tracks stay in at zero volume, left out ones are gone.
+        assert!(settings.plan().tracks == [(0, 0.0), (2, 1.5)]);
+        assert_eq!(settings.summary(), "2 of 3 tracks");
+        assert_eq!(AudioSettings::default().summary(), "No audio");
+    }
+
+    #[test]
+    fn reencode_only_when_the_audio_changes() {
+        let mut settings = AudioSettings::for_streams(2);
+        assert!(!settings.plan().needs_reencode());
+        settings.tracks[1].volume = 0.8;
+        assert!(settings.plan().needs_reencode());
+
+        let mut settings = AudioSettings::for_streams(2);
+        settings.mix_down = true;
+        assert!(settings.plan().needs_reencode());
+        // Nothing to mix with one track.
+        settings.tracks[1].include = false;
+        assert!(!settings.plan().needs_reencode());
+
+        let mut settings = AudioSettings::for_streams(1);
+        settings.normalize = true;
+        assert!(settings.plan().needs_reencode());
+        // Muting is a volume change too.
+        let mut settings = AudioSettings::for_streams(1);
+        settings.tracks[0].mute = true;
+        assert!(settings.plan().needs_reencode());
+    }
+}
@@ -0,0 +1,41 @@
+// On-disk cache for data that is slow to generate from a video (waveforms, thumbnails, ...).
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::UNIX_EPOCH,
+};
+
+// Counts up for `job_file`.
+static NEXT_JOB_FILE: AtomicU64 = AtomicU64::new(0);
+
+// Path of the cache file for `source`, or None if the source can't be read.
+// The key includes the size and modification time, so editing the video invalidates its cache.
+pub fn cache_file(source: &str, extension: &str) -> Option<PathBuf> {
+    let metadata = fs::metadata(source).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.hash(&mut hasher);
+
+    temp_file(&format!("{:016x}.{extension}", hasher.finish()))
+}
+
+// Path for a file called `name` in the app's temp folder, for things that aren't worth caching.
+pub fn temp_file(name: &str) -> Option<PathBuf> {
+    let dir = env::temp_dir().join("quick_trim");
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir.join(name))
+}
+
+// Path for a new temp file that belongs to one queued job, e.g. the chapters it reads. Every call gets a different
+// name, so queueing the same output again can't change the file an earlier job is still waiting to read.
+pub fn job_file(extension: &str) -> Option<PathBuf> {
+    let number = NEXT_JOB_FILE.fetch_add(1, Ordering::Relaxed);
+    temp_file(&format!("job-{}-{number}.{extension}", process::id()))
+}
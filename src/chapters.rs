@@ -0,0 +1,169 @@
+// Named markers dropped while scrubbing, and the lane under the timeline that shows them with the source's chapters.
+//
+// Markers turn into chapters for the export, each one running until the next marker. Either set of chapters can also
+// be used to split the video into a file per chapter.
+
+use std::ops::RangeInclusive;
+
+use eframe::egui::{self, Color32};
+
+use crate::{num_to_time, probe::Chapter};
+
+// How close to a marker a click has to be to pick it instead of the chapter under it, in pixels.
+const MARKER_HIT_DISTANCE: f32 = 5.0;
+
+#[derive(Clone, PartialEq)]
+pub struct Marker {
+    pub time: f32,
+    pub name: String,
+}
+
+// Where the exported clip's chapters come from.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ChapterSource {
+    #[default]
+    Original,
+    Markers,
+    None,
+}
+
+impl ChapterSource {
+    pub const ALL: [ChapterSource; 3] = [ChapterSource::Original, ChapterSource::Markers, ChapterSource::None];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChapterSource::Original => "From the Video",
+            ChapterSource::Markers => "From Markers",
+            ChapterSource::None => "None",
+        }
+    }
+}
+
+// Adds a marker at `time`, keeping the list in time order.
+pub fn add_marker(markers: &mut Vec<Marker>, time: f32) {
+    let name = format!("Marker {}", markers.len() + 1);
+    let index = markers.partition_point(|m| m.time <= time);
+    markers.insert(index, Marker { time, name });
+}
+
+// Chapters that run from each marker to the next one, the last to `duration`. Of markers at the same time only the
+// first one counts, and an untitled chapter covers the bit before the first marker so the chapters start at 0.
+pub fn marker_chapters(markers: &[Marker], duration: f32) -> Vec<Chapter> {
+    let mut sorted: Vec<&Marker> = markers.iter().filter(|m| m.time < duration).collect();
+    sorted.sort_by(|a, b| a.time.total_cmp(&b.time));
+    sorted.dedup_by(|next, previous| next.time == previous.time);
+    let mut chapters = vec![];
+    if let Some(first) = sorted.first().filter(|m| m.time > 0.0) {
+        chapters.push(Chapter {
+            start: 0.0,
+            end: first.time,
+            title: None,
+        });
+    }
+    chapters.extend(sorted.iter().enumerate().map(|(i, marker)| Chapter {
+        start: marker.time,
+        end: sorted.get(i + 1).map_or(duration, |next| next.time),
+        title: Some(marker.name.clone()).filter(|n| !n.trim().is_empty()),
+    }));
+    chapters
+}
+
+pub struct ChapterLaneOutput {
+    pub response: egui::Response,
+    pub clicked_chapter: Option<usize>,
+    pub clicked_marker: Option<usize>,
+}
+
+// Draws the chapters as blocks and the markers as flags for the `visible` time range. `empty_text` is shown when
+// there's neither.
+pub fn chapter_lane(
+    ui: &mut egui::Ui,
+    chapters: &[Chapter],
+    markers: &[Marker],
+    visible: RangeInclusive<f32>,
+    width: f32,
+    empty_text: &str,
+) -> ChapterLaneOutput {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 20.0), egui::Sense::click());
+    let mut output = ChapterLaneOutput {
+        response,
+        clicked_chapter: None,
+        clicked_marker: None,
+    };
+    if !ui.is_rect_visible(rect) {
+        return output;
+    }
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_hex("#252525").unwrap());
+    if chapters.is_empty() && markers.is_empty() {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            empty_text,
+            egui::FontId::default(),
+            Color32::GRAY,
+        );
+        return output;
+    }
+
+    let (start, end) = (*visible.start(), *visible.end());
+    let to_x = |time: f32| rect.left() + (time - start) / (end - start) * rect.width();
+    let pointer = output.response.hover_pos();
+    let hovered_marker = pointer.and_then(|pos| markers.iter().position(|m| (to_x(m.time) - pos.x).abs() <= MARKER_HIT_DISTANCE));
+    let hovered_chapter = pointer
+        .filter(|_| hovered_marker.is_none())
+        .and_then(|pos| chapters.iter().position(|c| (to_x(c.start)..=to_x(c.end)).contains(&pos.x)));
+
+    for (index, chapter) in chapters.iter().enumerate() {
+        let block = egui::Rect::from_x_y_ranges(to_x(chapter.start)..=to_x(chapter.end), rect.y_range()).shrink2(egui::vec2(1.0, 2.0));
+        if !block.intersects(rect) {
+            continue;
+        }
+        let color = match (hovered_chapter == Some(index), index % 2) {
+            (true, _) => Color32::from_rgb(90, 110, 160),
+            (false, 0) => Color32::from_rgb(55, 70, 105),
+            (false, _) => Color32::from_rgb(70, 60, 105),
+        };
+        painter.rect_filled(block, 3.0, color);
+        let title = chapter.title.clone().unwrap_or_else(|| format!("Chapter {}", index + 1));
+        painter.with_clip_rect(block.intersect(rect)).text(
+            block.left_center() + egui::vec2(4.0, 0.0),
+            egui::Align2::LEFT_CENTER,
+            title,
+            egui::FontId::proportional(11.0),
+            Color32::WHITE,
+        );
+    }
+    for (index, marker) in markers.iter().enumerate() {
+        let x = to_x(marker.time);
+        if !rect.x_range().contains(x) {
+            continue;
+        }
+        let color = if hovered_marker == Some(index) { Color32::WHITE } else { Color32::GOLD };
+        painter.vline(x, rect.y_range(), egui::Stroke::new(2.0, color));
+        let top = egui::pos2(x, rect.top());
+        painter.add(egui::Shape::convex_polygon(
+            vec![top, top + egui::vec2(7.0, 4.0), top + egui::vec2(0.0, 8.0)],
+            color,
+            egui::Stroke::NONE,
+        ));
+    }
+
+    if let Some(marker) = hovered_marker.map(|i| &markers[i]) {
+        output.response = output
+            .response
+            .on_hover_text_at_pointer(format!("{} ({})\nClick to jump here", marker.name, num_to_time(marker.time)));
+    } else if let Some((index, chapter)) = hovered_chapter.map(|i| (i, &chapters[i])) {
+        let title = chapter.title.clone().unwrap_or_else(|| format!("Chapter {}", index + 1));
+        output.response = output.response.on_hover_text_at_pointer(format!(
+            "{title} ({} - {})\nClick to trim to this chapter",
+            num_to_time(chapter.start),
+            num_to_time(chapter.end)
+        ));
+    }
+    if output.response.clicked() {
+        output.clicked_marker = hovered_marker;
+        output.clicked_chapter = hovered_chapter;
+    }
+    output
+}
@@ -0,0 +1,180 @@
+// Contact sheets: a grid of thumbnails spread evenly across the trim with their times burned in, under a header with
+// the file's name, length and size. Handy for cataloguing recordings.
+//
+// Every thumbnail is its own input, seeked to its time like the preview frames are, so only a few frames around each
+// one get decoded instead of the whole video. The filter graph takes a frame from each, stamps its time on and tiles
+// them into one image.
+
+use std::path::Path;
+
+use crate::{
+    frames::StillFormat,
+    num_to_time, overlay,
+    probe::{self, VideoInfo},
+    subtitles::escape_filter_value,
+};
+
+// Thumbnails fit in a square this size, so portrait videos get tall ones.
+const THUMBNAIL_SIZE: u32 = 320;
+const HEADER_HEIGHT: u32 = 48;
+// Around and between the thumbnails.
+const SPACING: u32 = 6;
+const BACKGROUND: &str = "0x202020";
+// Every thumbnail opens the video again, so past this the sheet gets slow and memory hungry.
+const MAX_THUMBNAILS: u32 = 60;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct SheetLayout {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        Self { columns: 4, rows: 5 }
+    }
+}
+
+impl SheetLayout {
+    // Most rows that fit under MAX_THUMBNAILS with `columns` columns.
+    pub fn max_rows(columns: u32) -> u32 {
+        (MAX_THUMBNAILS / columns.max(1)).max(1)
+    }
+
+    // The layout with the rows cut down to fit under MAX_THUMBNAILS.
+    pub fn fitted(self) -> Self {
+        let columns = self.columns.clamp(1, MAX_THUMBNAILS);
+        Self {
+            columns,
+            rows: self.rows.clamp(1, Self::max_rows(columns)),
+        }
+    }
+
+    // Where each thumbnail comes from: the middle of each of the equal slices of start..end, which keeps them off
+    // the very first and last frames.
+    pub fn times(&self, start: f32, end: f32) -> Vec<f32> {
+        let count = self.columns * self.rows;
+        let slice = (end - start).max(0.0) / count as f32;
+        (0..count).map(|i| start + (i as f32 + 0.5) * slice).collect()
+    }
+
+    // Size of the finished sheet.
+    pub fn size(&self, info: &VideoInfo) -> [u32; 2] {
+        let layout = self.fitted();
+        let [width, height] = probe::fit_size(info.display_aspect(), THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        [
+            layout.columns * (width + SPACING) + SPACING,
+            layout.rows * (height + SPACING) + SPACING + HEADER_HEIGHT,
+        ]
+    }
+}
+
+// Default file name for the sheet of `input`, e.g. "clip contact sheet.png".
+pub fn sheet_name(input: &str) -> String {
+    let stem = Path::new(input)
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    format!("{stem} contact sheet.png")
+}
+
+// e.g. "clip.mp4  |  00:12:34.56  |  1920x1080  |  29.970 fps", plus the range when only part of it is shown.
+fn header(input: &str, info: &VideoInfo, start: f32, end: f32) -> String {
+    let name = Path::new(input)
+        .file_name()
+        .map_or_else(|| input.to_owned(), |n| n.to_string_lossy().into_owned());
+    let [width, height] = info.display_size();
+    let mut parts = vec![
+        name,
+        num_to_time(info.duration),
+        format!("{width:.0}x{height:.0}"),
+        format!("{:.3} fps", info.frame_rate),
+    ];
+    if start > 0.0 || end < info.duration {
+        parts.push(format!("{} - {}", num_to_time(start), num_to_time(end)));
+    }
+    parts.join("  |  ")
+}
+
+// Arguments that render the contact sheet for start..end of `input` to `output`, a .png or .jpg.
+pub fn sheet_args(input: &str, info: &VideoInfo, start: f32, end: f32, layout: SheetLayout, output: &str) -> Vec<String> {
+    let layout = layout.fitted();
+    let times = layout.times(start, end);
+    let [width, height] = probe::fit_size(info.display_aspect(), THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let font = overlay::fontfile_option(&overlay::font_path(overlay::FONTS[0].1));
+
+    // The save dialog has already asked about replacing an existing file.
+    let mut args: Vec<String> = vec!["-y".into()];
+    for time in &times {
+        args.extend(["-ss".into(), num_to_time(*time), "-i".into(), input.to_owned()]);
+    }
+    let mut graph = String::new();
+    let mut thumbnails = String::new();
+    for (i, time) in times.iter().enumerate() {
+        graph.push_str(&format!(
+            "[{i}:v:0]trim=end_frame=1,setpts=PTS-STARTPTS,scale={width}:{height},setsar=1,\
+             drawtext={font}text={}:expansion=none:fontsize=h/10:fontcolor=white:\
+             box=1:boxcolor=black@0.6:boxborderw=4:x=w-text_w-8:y=h-text_h-8[t{i}];",
+            escape_filter_value(&num_to_time(*time))
+        ));
+        thumbnails.push_str(&format!("[t{i}]"));
+    }
+    graph.push_str(&format!(
+        "{thumbnails}concat=n={}:v=1:a=0,\
+         tile={}x{}:margin={SPACING}:padding={SPACING}:color={BACKGROUND},\
+         pad=iw:ih+{HEADER_HEIGHT}:0:{HEADER_HEIGHT}:color={BACKGROUND},\
+         drawtext={font}text={}:expansion=none:fontsize=20:fontcolor=white:x={SPACING}:y=({HEADER_HEIGHT}-text_h)/2",
+        times.len(),
+        layout.columns,
+        layout.rows,
+        escape_filter_value(&header(input, info, start, end))
+    ));
+    args.extend(["-filter_complex".into(), graph, "-frames:v".into(), "1".into()]);
+    args.extend(StillFormat::for_path(output).quality_args());
+    args.extend(["-update".into(), "1".into(), output.to_owned()]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::tests::dvd;
+
+    #[test]
+    fn times_are_spread_evenly() {
+        let layout = SheetLayout { columns: 4, rows: 5 };
+        let times = layout.times(10.0, 30.0);
+        assert_eq!(times.len() as u32, layout.columns * layout.rows);
+        assert_eq!(times[0], 10.5);
+        assert_eq!(times[19], 29.5);
+        assert!(times.iter().all(|time| (10.0..30.0).contains(time)));
+        assert!(times.windows(2).all(|pair| (pair[1] - pair[0] - 1.0).abs() < 1e-5));
+        // An empty range puts them all on the start.
+        assert_eq!(SheetLayout { columns: 2, rows: 1 }.times(5.0, 5.0), [5.0, 5.0]);
+    }
+
+    #[test]
+    fn big_layouts_are_capped() {
+        assert_eq!(SheetLayout::max_rows(10), 6);
+        assert_eq!(SheetLayout::max_rows(7), 8);
+        let layout = SheetLayout { columns: 10, rows: 20 }.fitted();
+        assert!(layout == SheetLayout { columns: 10, rows: 6 });
+        assert!(SheetLayout::default().fitted() == SheetLayout::default());
+        let args = sheet_args("clip.mp4", &dvd(), 0.0, 60.0, SheetLayout { columns: 10, rows: 20 }, "sheet.png");
+        assert_eq!(args.iter().filter(|arg| *arg == "-i").count(), 60);
+        assert!(args.iter().any(|arg| arg.contains("concat=n=60:v=1:a=0,tile=10x6:")));
+    }
+
+    #[test]
+    fn header_is_escaped() {
+        let info = dvd();
+        let args = sheet_args("/videos/it's [1], ok.mp4", &info, 0.0, info.duration, SheetLayout::default(), "sheet.jpg");
+        let graph = &args[args.iter().position(|arg| arg == "-filter_complex").unwrap() + 1];
+        let header = header("/videos/it's [1], ok.mp4", &info, 0.0, info.duration);
+        assert!(header.starts_with("it's [1], ok.mp4  |  00:25:00.00  |  "));
+        assert!(graph.contains(&format!("text={}:expansion=none:fontsize=20", escape_filter_value(&header))));
+        assert!(graph.contains(r"text=it\\\'s \[1\]\, ok.mp4  |  00\\:25\\:00.00  |  "));
+        // Each thumbnail's time too.
+        assert!(graph.contains(r"text=00\\:00\\:37.50:expansion=none"));
+        assert_eq!(args[args.len() - 5..], ["-q:v", "2", "-update", "1", "sheet.jpg"]);
+    }
+}
@@ -0,0 +1,110 @@
+// Which streams an output container can take as-is, for "keep everything" trims.
+//
+// This only covers the cases that come up with recordings and downloads. Anything it doesn't know about is
+// let through and left for ffmpeg to complain about.
+
+use std::path::Path;
+
+use crate::probe::StreamInfo;
+
+// Lowercase extension of `path`, e.g. "mp4".
+pub fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// MP4 and MOV, which share most of their rules and the `-movflags` option.
+pub fn is_mp4_family(extension: &str) -> bool {
+    matches!(extension, "mp4" | "m4v" | "mov")
+}
+
+// Why `stream` can't be copied into a file with this extension, or None if it can.
+pub fn unsupported_reason(stream: &StreamInfo, extension: &str) -> Option<String> {
+    let codec = stream.codec.as_str();
+    let can_hold = match (extension, stream.kind.as_str()) {
+        ("mkv" | "mka", _) => true,
+        ("webm", "video") => matches!(codec, "vp8" | "vp9" | "av1"),
+        ("webm", "audio") => matches!(codec, "opus" | "vorbis"),
+        ("webm", "subtitle") => codec == "webvtt",
+        ("webm", _) => false,
+        (ext, "video") if is_mp4_family(ext) => codec != "vp8",
+        (ext, "audio") if is_mp4_family(ext) => !codec.starts_with("pcm_") || ext == "mov",
+        // Text subtitles have to be converted to mov_text, which a copy can't do.
+        (ext, "subtitle") if is_mp4_family(ext) => codec == "mov_text",
+        (ext, "attachment") if is_mp4_family(ext) => false,
+        // Timecode and other data tracks only survive in MOV.
+        (ext, "data") if is_mp4_family(ext) => ext == "mov",
+        _ => true,
+    };
+    if can_hold {
+        None
+    } else {
+        Some(format!(
+            "Stream {} ({} {codec}) can't be copied into .{extension}",
+            stream.index, stream.kind
+        ))
+    }
+}
+
+// The streams that will be left out of a "keep everything" trim to `output`, with why.
+pub fn unsupported_streams<'a>(streams: &'a [StreamInfo], output: &str) -> Vec<(&'a StreamInfo, String)> {
+    let extension = extension(output);
+    streams
+        .iter()
+        .filter_map(|stream| unsupported_reason(stream, &extension).map(|reason| (stream, reason)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The streams ffprobe lists for an MKV recording with a commentary track, subtitles and an embedded font.
+    fn mkv_streams() -> Vec<StreamInfo> {
+        [
+            ("video", "h264"),
+            ("audio", "aac"),
+            ("audio", "pcm_s16le"),
+            ("subtitle", "subrip"),
+            ("attachment", "ttf"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (kind, codec))| StreamInfo {
+            index,
+            kind: kind.to_owned(),
+            codec: codec.to_owned(),
+        })
+        .collect()
+    }
+
+    fn dropped(streams: &[StreamInfo], output: &str) -> Vec<usize> {
+        unsupported_streams(streams, output).iter().map(|(stream, _)| stream.index).collect()
+    }
+
+    #[test]
+    fn streams_each_container_leaves_out() {
+        let streams = mkv_streams();
+        assert!(dropped(&streams, "out.mkv").is_empty());
+        assert_eq!(dropped(&streams, "out.mp4"), [2, 3, 4]);
+        // MOV takes PCM audio.
+        assert_eq!(dropped(&streams, "out.MOV"), [3, 4]);
+        assert_eq!(dropped(&streams, "out.webm"), [0, 1, 2, 3, 4]);
+        // Unknown containers are left to ffmpeg.
+        assert!(dropped(&streams, "out.ts").is_empty());
+        assert!(dropped(&[], "out.mp4").is_empty());
+    }
+
+    #[test]
+    fn reasons_name_the_stream() {
+        let streams = mkv_streams();
+        assert_eq!(
+            unsupported_reason(&streams[3], "mp4").as_deref(),
+            Some("Stream 3 (subtitle subrip) can't be copied into .mp4")
+        );
+        assert_eq!(extension("C:/Videos/Clip.M4V"), "m4v");
+        assert_eq!(extension("no_extension"), "");
+    }
+}
@@ -0,0 +1,456 @@
+// Crop rectangle for the export, the editor for drawing it on a preview frame, and `cropdetect` to find
+// letterbox bars automatically.
+//
+// The rectangle is stored as fractions of the displayed frame, so it stays the same whatever size the
+// preview is and doesn't care about non-square pixels. It's only turned into pixels for the `crop` filter.
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, pos2, vec2, Color32};
+
+use crate::{hidden_command, letterbox, num_to_time, probe::VideoInfo};
+
+pub const ASPECT_PRESETS: [(&str, f32); 3] = [("16:9", 16.0 / 9.0), ("9:16", 9.0 / 16.0), ("1:1", 1.0)];
+
+// Smallest the crop can be dragged down to, as a fraction of the frame.
+const MIN_SIZE: f32 = 0.05;
+const HANDLE_GRAB_DISTANCE: f32 = 8.0;
+// How much of the video cropdetect looks at, from the start trim.
+const DETECT_SECONDS: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Crop {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Crop {
+    pub const FULL: Crop = Crop {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    // The largest centered crop with `aspect`, for a frame displayed at `frame_aspect`.
+    pub fn with_aspect(frame_aspect: f32, aspect: f32) -> Self {
+        let (width, height) = if aspect < frame_aspect {
+            (aspect / frame_aspect, 1.0)
+        } else {
+            (1.0, frame_aspect / aspect)
+        };
+        Self {
+            x: (1.0 - width) / 2.0,
+            y: (1.0 - height) / 2.0,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.width >= 1.0 && self.height >= 1.0
+    }
+
+    // The size of the frames the filter chain sees. ffmpeg rotates them before any filters run.
+    fn frame_size(info: &VideoInfo) -> [u32; 2] {
+        if info.rotation % 180 == 90 {
+            [info.height, info.width]
+        } else {
+            [info.width, info.height]
+        }
+    }
+
+    // Crop in pixels of the frames the filter chain sees, rounded to even numbers for the encoder.
+    pub fn pixels(&self, info: &VideoInfo) -> [u32; 4] {
+        let [frame_width, frame_height] = Self::frame_size(info);
+        // Rounding up can't go past the frame, which for an odd sized one means the even size just under it.
+        let even = |fraction: f32, size: u32| ((fraction * size as f32 / 2.0).round() as u32 * 2).min(size / 2 * 2);
+        let width = even(self.width, frame_width).max(2);
+        let height = even(self.height, frame_height).max(2);
+        let x = even(self.x, frame_width).min(frame_width - width);
+        let y = even(self.y, frame_height).min(frame_height - height);
+        [width, height, x, y]
+    }
+
+    pub fn filter(&self, info: &VideoInfo) -> String {
+        let [width, height, x, y] = self.pixels(info);
+        format!("crop={width}:{height}:{x}:{y}")
+    }
+
+    fn from_pixels([width, height, x, y]: [u32; 4], info: &VideoInfo) -> Self {
+        let [frame_width, frame_height] = Self::frame_size(info);
+        let (frame_width, frame_height) = (frame_width.max(1) as f32, frame_height.max(1) as f32);
+        Self {
+            x: x as f32 / frame_width,
+            y: y as f32 / frame_height,
+            width: width as f32 / frame_width,
+            height: height as f32 / frame_height,
+        }
+    }
+}
+
+// Runs cropdetect over the first few seconds from `start`. The receiver gets None if nothing was found.
+pub fn detect(ctx: &egui::Context, path: &str, start: f32, info: &VideoInfo) -> Receiver<Option<Crop>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let info = info.clone();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let seconds = DETECT_SECONDS.to_string();
+        let output = hidden_command("ffmpeg")
+            .args([
+                "-ss",
+                &num_to_time(start),
+                "-i",
+                &path,
+                "-t",
+                &seconds,
+                "-an",
+                "-vf",
+                "cropdetect=round=2",
+                "-f",
+                "null",
+                "-",
+            ])
+            .output();
+        // cropdetect logs its current guess for every frame, the last one has seen the most.
+        let crop = output.ok().and_then(|output| {
+            let log = String::from_utf8_lossy(&output.stderr);
+            let last = log.rsplit("crop=").next().filter(|_| log.contains("crop="))?;
+            let values: Vec<u32> = last.split_whitespace().next()?.split(':').filter_map(|v| v.parse().ok()).collect();
+            let pixels: [u32; 4] = values.try_into().ok()?;
+            Some(Crop::from_pixels(pixels, &info))
+        });
+        let _ = sender.send(crop);
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+// Which edges of the crop a drag moves. None of them means the whole rectangle is being moved.
+#[derive(Clone, Copy, Default)]
+struct Grab {
+    left: bool,
+    right: bool,
+    top: bool,
+    bottom: bool,
+}
+
+// Draws `texture` letterboxed into `size` with the crop on top of it. The crop can be moved by dragging inside it
+// and resized from its edges and corners. With `aspect_lock` only the corners resize, keeping that aspect ratio.
+pub fn crop_editor(
+    ui: &mut egui::Ui,
+    crop: &mut Crop,
+    texture: Option<&egui::TextureHandle>,
+    frame_aspect: f32,
+    aspect_lock: Option<f32>,
+    size: egui::Vec2,
+) -> egui::Response {
+    let (rect, mut response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let id = response.id;
+    let frame = letterbox(rect, frame_aspect);
+    let to_screen = |x: f32, y: f32| pos2(frame.left() + x * frame.width(), frame.top() + y * frame.height());
+    let crop_rect = |crop: &Crop| egui::Rect::from_min_max(to_screen(crop.x, crop.y), to_screen(crop.x + crop.width, crop.y + crop.height));
+
+    let grab_at = |pos: egui::Pos2, crop_rect: egui::Rect| {
+        let near = |a: f32, b: f32| (a - b).abs() <= HANDLE_GRAB_DISTANCE;
+        let in_x = crop_rect.x_range().expand(HANDLE_GRAB_DISTANCE).contains(pos.x);
+        let in_y = crop_rect.y_range().expand(HANDLE_GRAB_DISTANCE).contains(pos.y);
+        let grab = Grab {
+            left: in_y && near(pos.x, crop_rect.left()),
+            right: in_y && near(pos.x, crop_rect.right()),
+            top: in_x && near(pos.y, crop_rect.top()),
+            bottom: in_x && near(pos.y, crop_rect.bottom()),
+        };
+        let corner = (grab.left || grab.right) && (grab.top || grab.bottom);
+        let edge = grab.left || grab.right || grab.top || grab.bottom;
+        if corner || (edge && aspect_lock.is_none()) {
+            Some(grab)
+        } else if crop_rect.contains(pos) {
+            Some(Grab::default())
+        } else {
+            None
+        }
+    };
+
+    if let Some(pos) = response.hover_pos() {
+        let cursor = match grab_at(pos, crop_rect(crop)).map(|g| (g.left, g.right, g.top, g.bottom)) {
+            Some((true, _, true, _) | (_, true, _, true)) => egui::CursorIcon::ResizeNwSe,
+            Some((true, _, _, true) | (_, true, true, _)) => egui::CursorIcon::ResizeNeSw,
+            Some((true, ..) | (_, true, ..)) => egui::CursorIcon::ResizeHorizontal,
+            Some((.., true, _) | (.., true)) => egui::CursorIcon::ResizeVertical,
+            Some(_) => egui::CursorIcon::Grab,
+            None => egui::CursorIcon::Default,
+        };
+        ui.ctx().set_cursor_icon(cursor);
+    }
+
+    if response.drag_started() {
+        let grab = response.interact_pointer_pos().and_then(|p| grab_at(p, crop_rect(crop)));
+        ui.data_mut(|d| d.insert_temp(id, grab));
+    }
+    let grab = ui.data(|d| d.get_temp::<Option<Grab>>(id)).flatten();
+    if let (Some(grab), true) = (grab, response.dragged()) {
+        let delta = response.drag_delta() / frame.size();
+        drag_crop(crop, grab, delta, frame_aspect, aspect_lock);
+        response.mark_changed();
+    }
+    if response.drag_stopped() {
+        ui.data_mut(|d| d.remove::<Option<Grab>>(id));
+    }
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::BLACK);
+    match texture {
+        Some(texture) => {
+            let uv = egui::Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+            painter.image(texture.id(), frame, uv, Color32::WHITE);
+        }
+        None => {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "No frame to show",
+                egui::FontId::default(),
+                Color32::GRAY,
+            );
+        }
+    }
+    // Everything outside the crop is dimmed.
+    let inner = crop_rect(crop);
+    let shade = Color32::from_black_alpha(160);
+    painter.rect_filled(egui::Rect::from_min_max(frame.min, pos2(frame.right(), inner.top())), 0.0, shade);
+    painter.rect_filled(egui::Rect::from_min_max(pos2(frame.left(), inner.bottom()), frame.max), 0.0, shade);
+    painter.rect_filled(
+        egui::Rect::from_min_max(pos2(frame.left(), inner.top()), pos2(inner.left(), inner.bottom())),
+        0.0,
+        shade,
+    );
+    painter.rect_filled(
+        egui::Rect::from_min_max(pos2(inner.right(), inner.top()), pos2(frame.right(), inner.bottom())),
+        0.0,
+        shade,
+    );
+    // Rule of thirds, to help line things up.
+    let thirds = egui::Stroke::new(1.0, Color32::from_white_alpha(60));
+    for i in 1..3 {
+        let f = i as f32 / 3.0;
+        painter.vline(inner.left() + inner.width() * f, inner.y_range(), thirds);
+        painter.hline(inner.x_range(), inner.top() + inner.height() * f, thirds);
+    }
+    painter.rect_stroke(inner, 0.0, egui::Stroke::new(1.5, Color32::WHITE));
+    for corner in [inner.left_top(), inner.right_top(), inner.left_bottom(), inner.right_bottom()] {
+        painter.rect_filled(egui::Rect::from_center_size(corner, vec2(8.0, 8.0)), 1.0, Color32::WHITE);
+    }
+    response
+}
+
+// `delta` is in fractions of the frame.
+fn drag_crop(crop: &mut Crop, grab: Grab, delta: egui::Vec2, frame_aspect: f32, aspect_lock: Option<f32>) {
+    let (mut left, mut top, mut right, mut bottom) = (crop.x, crop.y, crop.x + crop.width, crop.y + crop.height);
+    if !(grab.left || grab.right || grab.top || grab.bottom) {
+        let dx = delta.x.clamp(-left, 1.0 - right);
+        let dy = delta.y.clamp(-top, 1.0 - bottom);
+        crop.x += dx;
+        crop.y += dy;
+        return;
+    }
+    if grab.left {
+        left = (left + delta.x).clamp(0.0, right - MIN_SIZE);
+    }
+    if grab.right {
+        right = (right + delta.x).clamp(left + MIN_SIZE, 1.0);
+    }
+    if grab.top {
+        top = (top + delta.y).clamp(0.0, bottom - MIN_SIZE);
+    }
+    if grab.bottom {
+        bottom = (bottom + delta.y).clamp(top + MIN_SIZE, 1.0);
+    }
+    if let Some(aspect) = aspect_lock {
+        // The width follows the mouse and the height follows the width, shrinking both if the height runs out of room.
+        let room = if grab.top { bottom } else { 1.0 - top };
+        let mut width = right - left;
+        let mut height = width * frame_aspect / aspect;
+        if height > room {
+            height = room;
+            width = height * aspect / frame_aspect;
+        }
+        if grab.left {
+            left = right - width;
+        } else {
+            right = left + width;
+        }
+        if grab.top {
+            top = bottom - height;
+        } else {
+            bottom = top + height;
+        }
+    }
+    *crop = Crop {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    };
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::probe::tests::{dvd, phone};
+
+    pub fn assert_close(crop: Crop, expected: Crop) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        assert!(
+            close(crop.x, expected.x) && close(crop.y, expected.y) && close(crop.width, expected.width) && close(crop.height, expected.height),
+            "{crop:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn rotated_sources_swap_width_and_height() {
+        // Stored 1920x1080 but rotated, so the filters see 1080x1920.
+        let info = phone();
+        assert_eq!(Crop::FULL.pixels(&info), [1080, 1920, 0, 0]);
+        let crop = Crop {
+            x: 0.25,
+            y: 0.0,
+            width: 0.5,
+            height: 0.25,
+        };
+        assert_eq!(crop.filter(&info), "crop=540:480:270:0");
+        // A square out of the portrait frame.
+        assert_eq!(Crop::with_aspect(1080.0 / 1920.0, 1.0).pixels(&info), [1080, 1080, 0, 420]);
+    }
+
+    #[test]
+    fn pixels_are_even() {
+        let mut info = dvd();
+        info.width = 721;
+        info.height = 481;
+        let crop = Crop {
+            x: 0.1,
+            y: 0.1,
+            width: 0.5,
+            height: 0.5,
+        };
+        assert_eq!(crop.pixels(&info), [360, 240, 72, 48]);
+        // The full frame rounds down to the even size that fits.
+        assert_eq!(Crop::FULL.pixels(&info), [720, 480, 0, 0]);
+        // A sliver still gives the encoder something to work with.
+        let sliver = Crop {
+            x: 0.999,
+            y: 0.0,
+            width: 0.001,
+            height: 1.0,
+        };
+        assert_eq!(sliver.pixels(&info), [2, 480, 719, 0]);
+    }
+
+    #[test]
+    fn aspect_presets_are_centered() {
+        let widescreen = 16.0 / 9.0;
+        assert_close(Crop::with_aspect(widescreen, widescreen), Crop::FULL);
+        assert_close(
+            Crop::with_aspect(widescreen, 1.0),
+            Crop {
+                x: 0.21875,
+                y: 0.0,
+                width: 0.5625,
+                height: 1.0,
+            },
+        );
+        assert_close(
+            Crop::with_aspect(widescreen, 9.0 / 16.0),
+            Crop {
+                x: (1.0 - 0.31640625) / 2.0,
+                y: 0.0,
+                width: 0.31640625,
+                height: 1.0,
+            },
+        );
+        // Wider than the frame takes the full width instead.
+        assert_close(
+            Crop::with_aspect(1.0, widescreen),
+            Crop {
+                x: 0.0,
+                y: 0.21875,
+                width: 1.0,
+                height: 0.5625,
+            },
+        );
+    }
+
+    #[test]
+    fn drags_stay_inside_the_frame() {
+        let quarter = Crop {
+            x: 0.5,
+            y: 0.5,
+            width: 0.25,
+            height: 0.25,
+        };
+        let grab = |left, right, top, bottom| Grab { left, right, top, bottom };
+
+        // Moving keeps the size and stops at the edges.
+        let mut crop = quarter;
+        drag_crop(&mut crop, Grab::default(), vec2(1.0, -1.0), 1.0, None);
+        assert_close(
+            crop,
+            Crop {
+                x: 0.75,
+                y: 0.0,
+                width: 0.25,
+                height: 0.25,
+            },
+        );
+
+        let mut crop = quarter;
+        drag_crop(&mut crop, grab(false, true, false, false), vec2(2.0, 0.0), 1.0, None);
+        assert_close(
+            crop,
+            Crop {
+                x: 0.5,
+                y: 0.5,
+                width: 0.5,
+                height: 0.25,
+            },
+        );
+
+        // An edge dragged past the opposite one stops at the smallest size.
+        let mut crop = quarter;
+        drag_crop(&mut crop, grab(true, false, false, false), vec2(1.0, 0.0), 1.0, None);
+        assert_close(
+            crop,
+            Crop {
+                x: 0.75 - MIN_SIZE,
+                y: 0.5,
+                width: MIN_SIZE,
+                height: 0.25,
+            },
+        );
+
+        // A locked square on a 16:9 frame runs out of height first, and the width shrinks to match.
+        let mut crop = Crop {
+            x: 0.0,
+            y: 0.5,
+            width: 0.25,
+            height: 0.25,
+        };
+        drag_crop(&mut crop, grab(false, true, false, true), vec2(2.0, 0.0), 16.0 / 9.0, Some(1.0));
+        assert_close(
+            crop,
+            Crop {
+                x: 0.0,
+                y: 0.5,
+                width: 0.28125,
+                height: 0.5,
+            },
+        );
+    }
+}
@@ -0,0 +1,286 @@
+// Finds dead air (silence, and optionally black or frozen picture) so it can be trimmed off or cut out.
+//
+// `silencedetect`, `blackdetect` and `freezedetect` all run in one ffmpeg pass on a background thread. They only log
+// where each region starts and ends, so the results are read back out of ffmpeg's log.
+
+use std::{
+    ops::RangeInclusive,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, Color32};
+
+use crate::hidden_command;
+
+// Anything quieter than this counts as silence.
+const SILENCE_NOISE: &str = "-35dB";
+// Shortest region any of the detectors report, in seconds.
+const MIN_REGION_SECONDS: f32 = 0.5;
+// Regions this close together are treated as one when trimming the ends.
+const MERGE_GAP: f32 = 0.1;
+// Kept parts shorter than this aren't worth a segment.
+const MIN_SEGMENT_SECONDS: f32 = 0.05;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DeadAirKind {
+    Silence,
+    Black,
+    Freeze,
+}
+
+impl DeadAirKind {
+    pub fn color(self) -> Color32 {
+        match self {
+            DeadAirKind::Silence => Color32::from_rgb(255, 170, 60),
+            DeadAirKind::Black => Color32::from_rgb(150, 110, 255),
+            DeadAirKind::Freeze => Color32::from_rgb(90, 220, 200),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Region {
+    pub kind: DeadAirKind,
+    pub start: f32,
+    pub end: f32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct DetectOptions {
+    pub black: bool,
+    pub freeze: bool,
+}
+
+// Starts looking for dead air in `path`. The receiver gets None if ffmpeg couldn't read the video.
+pub fn detect(ctx: &egui::Context, path: &str, duration: f32, has_audio: bool, options: DetectOptions) -> Receiver<Option<Vec<Region>>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let _ = sender.send(find_regions(&path, duration, has_audio, options));
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+fn find_regions(path: &str, duration: f32, has_audio: bool, options: DetectOptions) -> Option<Vec<Region>> {
+    let mut args: Vec<String> = ["-hide_banner", "-i", path].map(String::from).to_vec();
+    if has_audio {
+        args.extend(["-af".into(), format!("silencedetect=n={SILENCE_NOISE}:d={MIN_REGION_SECONDS}")]);
+    } else {
+        args.push("-an".into());
+    }
+    let mut video_filters = vec![];
+    if options.black {
+        video_filters.push(format!("blackdetect=d={MIN_REGION_SECONDS}:pix_th=0.1"));
+    }
+    if options.freeze {
+        video_filters.push(format!("freezedetect=n=-60dB:d={MIN_REGION_SECONDS}"));
+    }
+    if video_filters.is_empty() {
+        args.push("-vn".into());
+    } else {
+        // The detectors don't need full size frames.
+        video_filters.insert(0, "scale=320:-2".to_owned());
+        args.extend(["-vf".into(), video_filters.join(",")]);
+    }
+    args.extend(["-sn", "-f", "null", "-"].map(String::from));
+    let output = hidden_command("ffmpeg").args(&args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_log(&String::from_utf8_lossy(&output.stderr), duration))
+}
+
+// The number after `key` in `line`, e.g. 12.5 for "black_start:12.5" or "silence_start: 12.5".
+fn value_after(line: &str, key: &str) -> Option<f32> {
+    let (_, rest) = line.split_once(key)?;
+    rest.trim_start().split(|c: char| c.is_whitespace() || c == '|').next()?.parse().ok()
+}
+
+// A region still open at the end of the log (silence that runs to the end of the file) ends at `duration`.
+fn parse_log(log: &str, duration: f32) -> Vec<Region> {
+    let mut regions = vec![];
+    let mut silence_start = None;
+    let mut freeze_start = None;
+    for line in log.lines() {
+        if let Some(start) = value_after(line, "silence_start:") {
+            silence_start = Some(start.max(0.0));
+        } else if let (Some(end), Some(start)) = (value_after(line, "silence_end:"), silence_start) {
+            regions.push(Region {
+                kind: DeadAirKind::Silence,
+                start,
+                end,
+            });
+            silence_start = None;
+        } else if let (Some(start), Some(end)) = (value_after(line, "black_start:"), value_after(line, "black_end:")) {
+            regions.push(Region {
+                kind: DeadAirKind::Black,
+                start,
+                end,
+            });
+        } else if let Some(start) = value_after(line, "freeze_start:") {
+            freeze_start = Some(start);
+        } else if let (Some(end), Some(start)) = (value_after(line, "freeze_end:"), freeze_start) {
+            regions.push(Region {
+                kind: DeadAirKind::Freeze,
+                start,
+                end,
+            });
+            freeze_start = None;
+        }
+    }
+    for (kind, start) in [(DeadAirKind::Silence, silence_start), (DeadAirKind::Freeze, freeze_start)] {
+        if let Some(start) = start {
+            regions.push(Region { kind, start, end: duration });
+        }
+    }
+    regions.sort_by(|a, b| a.start.total_cmp(&b.start));
+    regions
+}
+
+// Where the content starts and ends once the dead air at either end is left out. Overlapping regions
+// of any kind count as one stretch of dead air.
+pub fn content_range(regions: &[Region], duration: f32) -> (f32, f32) {
+    let mut merged: Vec<(f32, f32)> = vec![];
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.1 + MERGE_GAP => last.1 = last.1.max(region.end),
+            _ => merged.push((region.start, region.end)),
+        }
+    }
+    let start = merged.first().filter(|(start, _)| *start <= MERGE_GAP).map_or(0.0, |(_, end)| *end);
+    let end = merged
+        .last()
+        .filter(|(_, end)| *end >= duration - MERGE_GAP)
+        .map_or(duration, |(start, _)| *start);
+    if start < end {
+        (start, end)
+    } else {
+        (0.0, duration)
+    }
+}
+
+// The parts of start..end left once every silence at least `min_silence` long is cut out.
+pub fn keep_segments(regions: &[Region], start: f32, end: f32, min_silence: f32) -> Vec<(f32, f32)> {
+    let mut segments = vec![];
+    let mut from = start;
+    let silences = regions
+        .iter()
+        .filter(|r| r.kind == DeadAirKind::Silence && r.end - r.start >= min_silence);
+    for silence in silences {
+        if silence.end <= from || silence.start >= end {
+            continue;
+        }
+        if silence.start - from >= MIN_SEGMENT_SECONDS {
+            segments.push((from, silence.start));
+        }
+        from = silence.end;
+    }
+    if end - from >= MIN_SEGMENT_SECONDS {
+        segments.push((from, end));
+    }
+    segments
+}
+
+// For shading the regions on the timeline.
+pub fn shading(regions: &[Region]) -> Vec<(RangeInclusive<f32>, Color32)> {
+    regions.iter().map(|r| (r.start..=r.end, r.kind.color())).collect()
+}
+
+// Short description for the options grid, e.g. "3 silent, 1 black".
+pub fn summary(regions: &[Region]) -> String {
+    let count = |kind| regions.iter().filter(|r| r.kind == kind).count();
+    let mut parts = vec![format!("{} silent", count(DeadAirKind::Silence))];
+    for (kind, name) in [(DeadAirKind::Black, "black"), (DeadAirKind::Freeze, "frozen")] {
+        if count(kind) > 0 {
+            parts.push(format!("{} {name}", count(kind)));
+        }
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // stderr of a silencedetect + blackdetect + freezedetect pass over a 60 second clip, trimmed to the detector
+    // lines and the progress line. The last silence runs to the end of the file, so it never gets a silence_end.
+    const LOG: &str = "Press [q] to stop, [?] for help
+[silencedetect @ 0x5581f2e1c7c0] silence_start: -0.0213333
+[blackdetect @ 0x5581f2e5a100] black_start:0 black_end:1.8 black_duration:1.8
+[silencedetect @ 0x5581f2e1c7c0] silence_end: 2.41 | silence_duration: 2.43133
+[freezedetect @ 0x5581f2e5b440] lavfi.freezedetect.freeze_start: 12.5
+[freezedetect @ 0x5581f2e5b440] lavfi.freezedetect.freeze_duration: 3.2
+[freezedetect @ 0x5581f2e5b440] lavfi.freezedetect.freeze_end: 15.7
+[silencedetect @ 0x5581f2e1c7c0] silence_start: 30.5
+[silencedetect @ 0x5581f2e1c7c0] silence_end: 33.25 | silence_duration: 2.75
+[silencedetect @ 0x5581f2e1c7c0] silence_start: 58.12
+size=N/A time=00:01:00.00 bitrate=N/A speed= 187x
+";
+
+    fn region(kind: DeadAirKind, start: f32, end: f32) -> Region {
+        Region { kind, start, end }
+    }
+
+    #[test]
+    fn log_regions() {
+        use DeadAirKind::*;
+        assert_eq!(
+            parse_log(LOG, 60.0),
+            [
+                region(Black, 0.0, 1.8),
+                region(Silence, 0.0, 2.41),
+                region(Freeze, 12.5, 15.7),
+                region(Silence, 30.5, 33.25),
+                region(Silence, 58.12, 60.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_log() {
+        assert!(parse_log("", 60.0).is_empty());
+        assert_eq!(content_range(&[], 60.0), (0.0, 60.0));
+        assert_eq!(keep_segments(&[], 5.0, 20.0, 1.0), [(5.0, 20.0)]);
+    }
+
+    #[test]
+    fn ends_are_trimmed_off() {
+        assert_eq!(content_range(&parse_log(LOG, 60.0), 60.0), (2.41, 58.12));
+        // Nothing but dead air leaves the range alone rather than trimming it to nothing.
+        assert_eq!(content_range(&[region(DeadAirKind::Silence, 0.0, 60.0)], 60.0), (0.0, 60.0));
+    }
+
+    #[test]
+    fn silences_are_cut_out() {
+        let regions = parse_log(LOG, 60.0);
+        assert_eq!(keep_segments(&regions, 0.0, 60.0, 1.0), [(2.41, 30.5), (33.25, 58.12)]);
+        // Only the 2.75 second silence is long enough.
+        assert_eq!(keep_segments(&regions, 0.0, 60.0, 2.6), [(0.0, 30.5), (33.25, 60.0)]);
+        // A trim that starts inside a silence starts where it ends.
+        assert_eq!(keep_segments(&regions, 31.0, 50.0, 1.0), [(33.25, 50.0)]);
+    }
+
+    #[test]
+    fn silence_at_the_end() {
+        let regions = parse_log("[silencedetect @ 0x1] silence_start: 40\n", 45.0);
+        assert_eq!(regions, [region(DeadAirKind::Silence, 40.0, 45.0)]);
+        assert_eq!(content_range(&regions, 45.0), (0.0, 40.0));
+        assert_eq!(keep_segments(&regions, 0.0, 45.0, 1.0), [(0.0, 40.0)]);
+    }
+
+    #[test]
+    fn zero_length_range() {
+        let regions = parse_log(LOG, 60.0);
+        assert!(keep_segments(&regions, 10.0, 10.0, 1.0).is_empty());
+        assert_eq!(content_range(&[], 0.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn summary_counts() {
+        assert_eq!(summary(&parse_log(LOG, 60.0)), "3 silent, 1 black, 1 frozen");
+        assert_eq!(summary(&[]), "0 silent");
+    }
+}
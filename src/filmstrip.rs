@@ -0,0 +1,110 @@
+// Thumbnail filmstrip drawn across the scrubber bar.
+//
+// All thumbnails come out of a single ffmpeg pass: only keyframes are decoded, `fps` picks evenly
+// spaced frames and `tile` packs them into one image, which is cached on disk as a PNG.
+
+use std::{
+    fs,
+    ops::RangeInclusive,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, Color32, ColorImage};
+
+use crate::{cache, hidden_command, load_image_from_memory};
+
+const COLUMNS: usize = 10;
+const ROWS: usize = 6;
+const THUMBNAIL_COUNT: usize = COLUMNS * ROWS;
+const THUMBNAIL_HEIGHT: u32 = 54;
+
+pub struct Filmstrip {
+    pub texture: egui::TextureHandle,
+    pub duration: f32,
+}
+
+// Starts generating the filmstrip for `path`. The receiver gets None if ffmpeg couldn't make one.
+pub fn load(ctx: &egui::Context, path: &str, duration: f32) -> Receiver<Option<ColorImage>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let cache_path = cache::cache_file(&path, "filmstrip.png");
+        let png = match cache_path.as_ref().and_then(|p| fs::read(p).ok()) {
+            Some(png) => Some(png),
+            None => {
+                let png = generate(&path, duration);
+                if let (Some(png), Some(cache_path)) = (&png, &cache_path) {
+                    let _ = fs::write(cache_path, png);
+                }
+                png
+            }
+        };
+        let _ = sender.send(png.and_then(|png| load_image_from_memory(&png).ok()));
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+fn generate(path: &str, duration: f32) -> Option<Vec<u8>> {
+    if duration <= 0.0 {
+        return None;
+    }
+    let filter = format!("fps={THUMBNAIL_COUNT}/{duration},scale=-2:{THUMBNAIL_HEIGHT},tile={COLUMNS}x{ROWS}");
+    let output = hidden_command("ffmpeg")
+        .args([
+            "-v",
+            "error",
+            "-skip_frame",
+            "nokey",
+            "-i",
+            path,
+            "-an",
+            "-vf",
+            &filter,
+            "-frames:v",
+            "1",
+            "-c:v",
+            "png",
+            "-f",
+            "image2pipe",
+            "pipe:1",
+        ])
+        .output()
+        .ok()?;
+    if output.stdout.is_empty() {
+        None
+    } else {
+        Some(output.stdout)
+    }
+}
+
+impl Filmstrip {
+    // Fills `rect` with as many thumbnails as fit at their own aspect ratio, each showing the
+    // thumbnail closest to the time under it.
+    pub fn paint(&self, painter: &egui::Painter, rect: egui::Rect, visible: RangeInclusive<f32>) {
+        let tile_size = egui::vec2(self.texture.size()[0] as f32 / COLUMNS as f32, self.texture.size()[1] as f32 / ROWS as f32);
+        let slot_width = rect.height() * tile_size.x / tile_size.y;
+        if slot_width <= 0.0 || self.duration <= 0.0 {
+            return;
+        }
+        let (start, end) = (*visible.start(), *visible.end());
+        let mut x = rect.left();
+        while x < rect.right() {
+            let slot = egui::Rect::from_min_size(egui::pos2(x, rect.top()), egui::vec2(slot_width, rect.height()));
+            let t = start + (slot.center().x - rect.left()) / rect.width() * (end - start);
+            let index = ((t / self.duration * THUMBNAIL_COUNT as f32) as usize).min(THUMBNAIL_COUNT - 1);
+            let (column, row) = ((index % COLUMNS) as f32, (index / COLUMNS) as f32);
+            let uv = egui::Rect::from_min_size(
+                egui::pos2(column / COLUMNS as f32, row / ROWS as f32),
+                egui::vec2(1.0 / COLUMNS as f32, 1.0 / ROWS as f32),
+            );
+            // The last slot is cut off at the end of the bar rather than squashed.
+            let clipped = slot.intersect(rect);
+            let uv = egui::Rect::from_min_size(uv.min, egui::vec2(uv.width() * clipped.width() / slot.width(), uv.height()));
+            painter.image(self.texture.id(), clipped, uv, Color32::WHITE);
+            x += slot_width;
+        }
+    }
+}
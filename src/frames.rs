@@ -0,0 +1,260 @@
+// Full size stills: single frames saved as images or copied to the clipboard, and image sequences of the trim.
+//
+// Frames come out the way the video is displayed, the right way up and with square pixels, but without the crop,
+// overlays or any of the other export settings.
+
+use std::{
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui;
+
+use crate::{get_filtered_frame, num_to_time};
+
+// Stretches non-square pixels out to the size the frame is displayed at. ffmpeg already applies the rotation.
+const DISPLAY_FILTER: &str = "scale=trunc(iw*sar/2)*2:ih,setsar=1";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StillFormat {
+    Png,
+    Jpeg,
+}
+
+impl StillFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            StillFormat::Png => "PNG",
+            StillFormat::Jpeg => "JPEG",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            StillFormat::Png => "png",
+            StillFormat::Jpeg => "jpg",
+        }
+    }
+
+    // JPEG for .jpg/.jpeg files, otherwise PNG.
+    pub fn for_path(path: &str) -> Self {
+        match Path::new(path).extension().map(|e| e.to_string_lossy().to_lowercase()) {
+            Some(extension) if extension == "jpg" || extension == "jpeg" => StillFormat::Jpeg,
+            _ => StillFormat::Png,
+        }
+    }
+
+    // PNG is lossless. JPEG defaults to a fairly low quality, so it's turned up close to the best.
+    pub fn quality_args(self) -> Vec<String> {
+        match self {
+            StillFormat::Png => vec![],
+            StillFormat::Jpeg => vec!["-q:v".into(), "2".into()],
+        }
+    }
+}
+
+// Which time a still is taken from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FrameSource {
+    Playhead,
+    Start,
+    End,
+}
+
+impl FrameSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameSource::Playhead => "Playhead",
+            FrameSource::Start => "Start Handle",
+            FrameSource::End => "End Handle",
+        }
+    }
+}
+
+// Default file name for a still of `input` at `time`, e.g. "clip 00-01-23.45.png". Colons aren't allowed in
+// Windows file names.
+pub fn still_name(input: &str, time: f32, format: StillFormat) -> String {
+    let stem = Path::new(input)
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    format!("{stem} {}.{}", num_to_time(time).replace(':', "-"), format.extension())
+}
+
+// The save dialog has already asked about replacing an existing file, so this always overwrites.
+pub fn still_args(input: &str, time: f32, output: &str) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-y".into(), "-ss".into(), num_to_time(time), "-i".into(), input.to_owned()];
+    args.extend(["-vf".into(), DISPLAY_FILTER.into(), "-frames:v".into(), "1".into()]);
+    args.extend(StillFormat::for_path(output).quality_args());
+    // Without -update the image2 muxer expects a numbered pattern in the file name.
+    args.extend(["-update".into(), "1".into(), output.to_owned()]);
+    args
+}
+
+// Every `step`th frame of start..end into `folder`, numbered from 1, e.g. "clip_000001.png".
+pub fn sequence_args(input: &str, start: f32, end: f32, step: u32, folder: &str, format: StillFormat, overwrite: bool) -> Vec<String> {
+    let mut args: Vec<String> = vec![];
+    if overwrite {
+        args.push("-y".into());
+    }
+    args.extend([
+        "-ss".into(),
+        num_to_time(start),
+        "-to".into(),
+        num_to_time(end),
+        "-i".into(),
+        input.to_owned(),
+    ]);
+    let mut filters = vec![];
+    if step > 1 {
+        filters.push(format!("select='not(mod(n\\,{step}))'"));
+    }
+    filters.push(DISPLAY_FILTER.to_owned());
+    // vfr keeps select's gaps from being filled with copies of the frames it kept.
+    args.extend(["-vf".into(), filters.join(","), "-fps_mode".into(), "vfr".into()]);
+    args.extend(format.quality_args());
+    let stem = Path::new(input)
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    let pattern = Path::new(folder).join(format!("{stem}_%06d.{}", format.extension()));
+    args.push(pattern.display().to_string());
+    args
+}
+
+// Roughly how many images a sequence makes: the frames in the trim, then every `step`th one starting with the first.
+// A length that's a hair over a whole number of frames from float error doesn't count as one more.
+pub fn sequence_count(length: f32, frame_rate: f32, step: u32) -> u32 {
+    let frames = (length * frame_rate - 0.001).ceil().max(0.0) as u32;
+    frames.div_ceil(step.max(1))
+}
+
+// Grabs the frame at `time` and puts it on the clipboard on a background thread. The receiver gets the reason if it
+// didn't work.
+pub fn copy_to_clipboard(ctx: &egui::Context, input: &str, time: f32) -> Receiver<Result<(), String>> {
+    let (sender, receiver) = channel();
+    let input = input.to_owned();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let result = get_filtered_frame(&input, &num_to_time(time), DISPLAY_FILTER)
+            .ok_or_else(|| "ffmpeg couldn't read the frame".to_owned())
+            .and_then(|image| {
+                let bytes = image.pixels.iter().flat_map(|pixel| pixel.to_srgba_unmultiplied()).collect::<Vec<u8>>();
+                let image = arboard::ImageData {
+                    width: image.size[0],
+                    height: image.size[1],
+                    bytes: bytes.into(),
+                };
+                let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+                clipboard.set_image(image).map_err(|e| e.to_string())
+            });
+        let _ = sender.send(result);
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn still() {
+        assert_eq!(
+            still_args("clip.mov", 1.5, "clip.jpg"),
+            [
+                "-y",
+                "-ss",
+                "00:00:01.50",
+                "-i",
+                "clip.mov",
+                "-vf",
+                DISPLAY_FILTER,
+                "-frames:v",
+                "1",
+                "-q:v",
+                "2",
+                "-update",
+                "1",
+                "clip.jpg"
+            ]
+        );
+        assert_eq!(
+            still_args("clip.mov", 0.0, "clip.PNG"),
+            [
+                "-y",
+                "-ss",
+                "00:00:00.00",
+                "-i",
+                "clip.mov",
+                "-vf",
+                DISPLAY_FILTER,
+                "-frames:v",
+                "1",
+                "-update",
+                "1",
+                "clip.PNG"
+            ]
+        );
+        assert_eq!(still_name("/videos/clip.mov", 83.45, StillFormat::Png), "clip 00-01-23.45.png");
+    }
+
+    #[test]
+    fn every_frame() {
+        let args = sequence_args("/videos/clip.mov", 2.0, 4.0, 1, "/frames", StillFormat::Png, false);
+        assert_eq!(
+            args,
+            [
+                "-ss",
+                "00:00:02.00",
+                "-to",
+                "00:00:04.00",
+                "-i",
+                "/videos/clip.mov",
+                "-vf",
+                DISPLAY_FILTER,
+                "-fps_mode",
+                "vfr",
+                "/frames/clip_%06d.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn every_nth_frame() {
+        let args = sequence_args("clip.mov", 0.0, 10.0, 5, "out", StillFormat::Jpeg, true);
+        assert_eq!(
+            args,
+            [
+                "-y",
+                "-ss",
+                "00:00:00.00",
+                "-to",
+                "00:00:10.00",
+                "-i",
+                "clip.mov",
+                "-vf",
+                "select='not(mod(n\\,5))',scale=trunc(iw*sar/2)*2:ih,setsar=1",
+                "-fps_mode",
+                "vfr",
+                "-q:v",
+                "2",
+                "out/clip_%06d.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn image_counts() {
+        assert_eq!(sequence_count(1.0, 30.0, 1), 30);
+        // Frames 0, 7, 14, 21 and 28.
+        assert_eq!(sequence_count(1.0, 30.0, 7), 5);
+        assert_eq!(sequence_count(1.0, 30.0, 30), 1);
+        assert_eq!(sequence_count(1.0, 30.0, 1000), 1);
+        // 299.7 frames means the last one is only partly in the trim but still comes out.
+        assert_eq!(sequence_count(10.0, 29.97, 1), 300);
+        // 7.2s in f32 times 30 comes out a hair over 216.
+        assert_eq!(sequence_count(7.3 - 0.1, 30.0, 1), 216);
+        assert_eq!(sequence_count(0.0, 30.0, 1), 0);
+        assert_eq!(sequence_count(-1.0, 30.0, 0), 0);
+    }
+}
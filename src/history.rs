@@ -0,0 +1,187 @@
+// Undo/redo stack for the values the user edits while setting up a trim.
+
+use crate::{
+    audio::AudioSettings,
+    chapters::{ChapterSource, Marker},
+    crop::Crop,
+    overlay::Overlay,
+    reframe::Reframe,
+    split::SplitSettings,
+    subtitles::SubtitleSettings,
+    trim::{Fades, Speed},
+};
+
+// How many edits are kept before the oldest ones are dropped.
+const MAX_HISTORY: usize = 200;
+
+// Everything that gets restored on undo/redo. Anything the app derives from these
+// (preview frames, video length, ...) is rebuilt after a restore instead of stored here.
+#[derive(Clone, PartialEq)]
+pub struct TrimState {
+    pub picked_path: Option<String>,
+    pub start_trim: f32,
+    pub end_trim: f32,
+    pub trim_to_end: bool,
+    pub slow_trim: bool,
+    pub overwrite: bool,
+    pub output_location: Option<String>,
+    pub crop: Crop,
+    pub output_height: Option<u32>,
+    pub reframe: Reframe,
+    pub audio: AudioSettings,
+    pub keep_all_streams: bool,
+    pub fades: Fades,
+    pub speed: Speed,
+    pub subtitles: SubtitleSettings,
+    pub overlays: Vec<Overlay>,
+    pub remove_silences: bool,
+    pub min_silence: f32,
+    pub markers: Vec<Marker>,
+    pub chapter_source: ChapterSource,
+    pub split: SplitSettings,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<TrimState>,
+    redo_stack: Vec<TrimState>,
+    current: Option<TrimState>,
+}
+
+impl History {
+    // Called once the user has finished an interaction (nothing is being dragged).
+    // If the state changed since the last call, the previous state becomes an undo step.
+    pub fn observe(&mut self, state: TrimState) {
+        match &self.current {
+            Some(current) if *current == state => {}
+            Some(_) => {
+                let previous = self.current.replace(state).unwrap();
+                self.undo_stack.push(previous);
+                if self.undo_stack.len() > MAX_HISTORY {
+                    self.undo_stack.remove(0);
+                }
+                self.redo_stack.clear();
+            }
+            None => self.current = Some(state),
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<TrimState> {
+        let state = self.undo_stack.pop()?;
+        if let Some(current) = self.current.replace(state.clone()) {
+            self.redo_stack.push(current);
+        }
+        Some(state)
+    }
+
+    pub fn redo(&mut self) -> Option<TrimState> {
+        let state = self.redo_stack.pop()?;
+        if let Some(current) = self.current.replace(state.clone()) {
+            self.undo_stack.push(current);
+        }
+        Some(state)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A state that only differs from the others in where the trim starts.
+    fn state(start_trim: f32) -> TrimState {
+        TrimState {
+            picked_path: Some("clip.mp4".to_owned()),
+            start_trim,
+            end_trim: 1000.0,
+            trim_to_end: false,
+            slow_trim: false,
+            overwrite: false,
+            output_location: None,
+            crop: Crop::FULL,
+            output_height: None,
+            reframe: Reframe::default(),
+            audio: AudioSettings::default(),
+            keep_all_streams: false,
+            fades: Fades::default(),
+            speed: Speed::default(),
+            subtitles: SubtitleSettings::default(),
+            overlays: vec![],
+            remove_silences: false,
+            min_silence: 1.0,
+            markers: vec![],
+            chapter_source: ChapterSource::default(),
+            split: SplitSettings::default(),
+        }
+    }
+
+    fn start(state: Option<TrimState>) -> Option<f32> {
+        state.map(|s| s.start_trim)
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::default();
+        history.observe(state(0.0));
+        // The first state is only the starting point.
+        assert!(!history.can_undo());
+        history.observe(state(0.0));
+        assert!(!history.can_undo());
+        history.observe(state(1.0));
+        history.observe(state(2.0));
+        assert_eq!(start(history.undo()), Some(1.0));
+        assert_eq!(start(history.undo()), Some(0.0));
+        assert_eq!(start(history.undo()), None);
+        assert_eq!(start(history.redo()), Some(1.0));
+        assert_eq!(start(history.redo()), Some(2.0));
+        assert_eq!(start(history.redo()), None);
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let mut history = History::default();
+        for start_trim in [0.0, 1.0, 2.0] {
+            history.observe(state(start_trim));
+        }
+        history.undo();
+        assert!(history.can_redo());
+        // Restoring a state and observing it again isn't a new edit.
+        history.observe(state(1.0));
+        assert!(history.can_redo());
+        history.observe(state(5.0));
+        assert!(!history.can_redo());
+        assert_eq!(start(history.undo()), Some(1.0));
+    }
+
+    #[test]
+    fn oldest_edits_are_dropped_at_the_limit() {
+        let mut history = History::default();
+        let edits = MAX_HISTORY + 5;
+        for start_trim in 0..=edits {
+            history.observe(state(start_trim as f32));
+        }
+        let mut undone = 0;
+        let mut oldest = None;
+        while let Some(state) = history.undo() {
+            undone += 1;
+            oldest = Some(state.start_trim);
+        }
+        assert_eq!(undone, MAX_HISTORY);
+        assert_eq!(oldest, Some(5.0));
+        // Everything undone can be redone, and redoing doesn't grow the stack past the limit.
+        let mut redone = 0;
+        while history.redo().is_some() {
+            redone += 1;
+        }
+        assert_eq!(redone, MAX_HISTORY);
+        assert_eq!(history.undo_stack.len(), MAX_HISTORY);
+        assert_eq!(start(history.current.clone()), Some(edits as f32));
+    }
+}
@@ -0,0 +1,285 @@
+// Hardware video encoding and decoding for re-encodes.
+//
+// An encoder showing up in `ffmpeg -encoders` only means ffmpeg was built with it, not that the machine has the GPU
+// or driver for it, so each one is also tried on a few blank frames before it's offered. Exports that still fail with
+// the hardware encoder are run again in software by the queue.
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::hidden_command;
+
+// Render node VAAPI uses. This is the first GPU on almost every Linux machine.
+const VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum VideoEncoder {
+    // Whatever ffmpeg picks for the output format, libx264 for MP4.
+    #[default]
+    Software,
+    Nvenc,
+    QuickSync,
+    Amf,
+    Vaapi,
+}
+
+impl VideoEncoder {
+    pub const HARDWARE: [VideoEncoder; 4] = [VideoEncoder::Nvenc, VideoEncoder::QuickSync, VideoEncoder::Amf, VideoEncoder::Vaapi];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VideoEncoder::Software => "Software (CPU)",
+            VideoEncoder::Nvenc => "NVIDIA NVENC",
+            VideoEncoder::QuickSync => "Intel Quick Sync",
+            VideoEncoder::Amf => "AMD AMF",
+            VideoEncoder::Vaapi => "VAAPI",
+        }
+    }
+
+    pub fn is_hardware(self) -> bool {
+        self != VideoEncoder::Software
+    }
+
+    fn codec(self) -> Option<&'static str> {
+        match self {
+            VideoEncoder::Software => None,
+            VideoEncoder::Nvenc => Some("h264_nvenc"),
+            VideoEncoder::QuickSync => Some("h264_qsv"),
+            VideoEncoder::Amf => Some("h264_amf"),
+            VideoEncoder::Vaapi => Some("h264_vaapi"),
+        }
+    }
+
+    // The `-hwaccel` that decodes on the same hardware.
+    fn hwaccel(self) -> Option<&'static str> {
+        match self {
+            VideoEncoder::Software => None,
+            VideoEncoder::Nvenc => Some("cuda"),
+            VideoEncoder::QuickSync => Some("qsv"),
+            VideoEncoder::Amf => Some("d3d11va"),
+            VideoEncoder::Vaapi => Some("vaapi"),
+        }
+    }
+
+    // Options that go before the inputs. `decode` also moves decoding onto the GPU.
+    pub fn input_args(self, decode: bool) -> Vec<String> {
+        let mut args = vec![];
+        if self == VideoEncoder::Vaapi {
+            args.extend(["-vaapi_device".into(), VAAPI_DEVICE.into()]);
+        }
+        if let (true, Some(hwaccel)) = (decode, self.hwaccel()) {
+            args.extend(["-hwaccel".into(), hwaccel.into()]);
+        }
+        args
+    }
+
+    // Filter that hands the frames over to the encoder, for encoders that only take frames already on the GPU.
+    pub fn upload_filter(self) -> Option<&'static str> {
+        (self == VideoEncoder::Vaapi).then_some("format=nv12,hwupload")
+    }
+
+    // `-c:v` and a quality setting roughly matching libx264's default of CRF 23. Nothing for software, so
+    // ffmpeg keeps choosing the encoder that fits the output format.
+    pub fn output_args(self) -> Vec<String> {
+        let Some(codec) = self.codec() else {
+            return vec![];
+        };
+        let quality: &[&str] = match self {
+            VideoEncoder::Nvenc => &["-cq", "23"],
+            VideoEncoder::QuickSync => &["-global_quality", "23"],
+            VideoEncoder::Amf => &["-rc", "cqp", "-qp_i", "23", "-qp_p", "23"],
+            VideoEncoder::Vaapi => &["-qp", "23"],
+            VideoEncoder::Software => &[],
+        };
+        let mut args = vec!["-c:v".to_owned(), codec.to_owned()];
+        args.extend(quality.iter().map(|s| s.to_string()));
+        args
+    }
+}
+
+// What this machine's ffmpeg can do in hardware.
+#[derive(Default)]
+pub struct Capabilities {
+    // From `ffmpeg -hwaccels`.
+    pub hwaccels: Vec<String>,
+    // Hardware encoders that are built in and actually worked on the test frames.
+    pub encoders: Vec<VideoEncoder>,
+}
+
+impl Capabilities {
+    // `encoder` if it was detected, otherwise software.
+    pub fn resolve(&self, encoder: VideoEncoder) -> VideoEncoder {
+        if self.encoders.contains(&encoder) {
+            encoder
+        } else {
+            VideoEncoder::Software
+        }
+    }
+
+    pub fn can_decode(&self, encoder: VideoEncoder) -> bool {
+        encoder.hwaccel().is_some_and(|hwaccel| self.hwaccels.iter().any(|h| h == hwaccel))
+    }
+}
+
+// Output of an ffmpeg run that only lists something, None if ffmpeg couldn't be run.
+fn ffmpeg_list(arg: &str) -> Option<String> {
+    let output = hidden_command("ffmpeg").args(["-hide_banner", arg]).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `ffmpeg -hwaccels` prints a header line and then one method per line.
+fn parse_hwaccels(text: &str) -> Vec<String> {
+    text.lines().skip(1).map(str::trim).filter(|l| !l.is_empty()).map(str::to_owned).collect()
+}
+
+// `ffmpeg -encoders` lines look like " V....D h264_nvenc           NVIDIA NVENC H.264 encoder". The legend at the
+// top gets picked up too, which doesn't matter since only known names are looked for.
+fn parse_encoders(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_owned)
+        .collect()
+}
+
+// Encodes a handful of blank frames to nowhere.
+fn encoder_works(encoder: VideoEncoder) -> bool {
+    let mut args = encoder.input_args(false);
+    args.extend(["-hide_banner", "-f", "lavfi", "-i", "color=c=black:s=256x256:d=0.2"].map(String::from));
+    if let Some(upload) = encoder.upload_filter() {
+        args.extend(["-vf".into(), upload.into()]);
+    }
+    args.extend(encoder.output_args());
+    args.extend(["-f", "null", "-"].map(String::from));
+    hidden_command("ffmpeg").args(&args).output().is_ok_and(|output| output.status.success())
+}
+
+// Finds the hardware encoders on a background thread, since test encodes can take a second each.
+pub fn detect(ctx: &egui::Context) -> Receiver<Capabilities> {
+    let (sender, receiver) = channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let hwaccels = ffmpeg_list("-hwaccels").map(|text| parse_hwaccels(&text)).unwrap_or_default();
+        let built_in = ffmpeg_list("-encoders").map(|text| parse_encoders(&text)).unwrap_or_default();
+        let encoders = VideoEncoder::HARDWARE
+            .into_iter()
+            .filter(|encoder| encoder.codec().is_some_and(|codec| built_in.iter().any(|e| e == codec)))
+            .filter(|encoder| encoder_works(*encoder))
+            .collect();
+        let _ = sender.send(Capabilities { hwaccels, encoders });
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+// Encoder choice for the settings window. `capabilities` is None while detection is still running.
+pub fn encoder_settings_ui(ui: &mut egui::Ui, capabilities: Option<&Capabilities>, encoder: &mut VideoEncoder, hardware_decode: &mut bool) {
+    ui.radio_value(encoder, VideoEncoder::Software, VideoEncoder::Software.name());
+    for hardware in VideoEncoder::HARDWARE {
+        let detected = capabilities.is_some_and(|c| c.encoders.contains(&hardware));
+        let radio = ui
+            .add_enabled(detected, egui::RadioButton::new(*encoder == hardware, hardware.name()))
+            .on_disabled_hover_text("Not available on this machine");
+        if radio.clicked() {
+            *encoder = hardware;
+        }
+    }
+    match capabilities {
+        None => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Checking for hardware encoders...");
+            });
+        }
+        Some(capabilities) if capabilities.encoders.is_empty() => {
+            ui.label("No hardware encoders were found, so re-encodes use the CPU.");
+        }
+        Some(capabilities) => {
+            ui.add_enabled(
+                capabilities.can_decode(*encoder),
+                egui::Checkbox::new(hardware_decode, "Decode on the GPU too"),
+            )
+            .on_hover_text("Also uses the GPU to decode the source. Turn this off if exports come out garbled.");
+        }
+    }
+    ui.label("If the hardware encoder fails, the export is redone in software and the log says why.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `ffmpeg -hide_banner -hwaccels` on a Linux build with NVIDIA and Intel support.
+    const HWACCELS: &str = "Hardware acceleration methods:
+vdpau
+cuda
+vaapi
+qsv
+drm
+opencl
+vulkan
+
+";
+
+    // Trimmed `ffmpeg -hide_banner -encoders`, legend included.
+    const ENCODERS: &str = "Encoders:
+ V..... = Video
+ A..... = Audio
+ S..... = Subtitle
+ .F.... = Frame-level multithreading
+ ..S... = Slice-level multithreading
+ ...X.. = Codec is experimental
+ ....B. = Supports draw_horiz_band
+ .....D = Supports direct rendering method 1
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)
+ V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)
+ V..... h264_vaapi           H.264/AVC (VAAPI) (codec h264)
+ A....D aac                  AAC (Advanced Audio Coding)
+";
+
+    #[test]
+    fn hwaccels_skip_the_header() {
+        assert_eq!(parse_hwaccels(HWACCELS), ["vdpau", "cuda", "vaapi", "qsv", "drm", "opencl", "vulkan"]);
+        assert!(parse_hwaccels("").is_empty());
+        assert!(parse_hwaccels("Hardware acceleration methods:\n\n").is_empty());
+    }
+
+    #[test]
+    fn encoders_are_listed_by_name() {
+        let encoders = parse_encoders(ENCODERS);
+        for name in ["libx264", "h264_nvenc", "h264_vaapi", "aac"] {
+            assert!(encoders.iter().any(|e| e == name), "{name} missing");
+        }
+        assert!(!encoders.iter().any(|e| e == "h264_amf"));
+        assert!(parse_encoders("").is_empty());
+    }
+
+    #[test]
+    fn resolve_falls_back_to_software() {
+        let capabilities = Capabilities {
+            hwaccels: parse_hwaccels(HWACCELS),
+            encoders: vec![VideoEncoder::Vaapi],
+        };
+        assert!(capabilities.resolve(VideoEncoder::Vaapi) == VideoEncoder::Vaapi);
+        assert!(capabilities.resolve(VideoEncoder::Nvenc) == VideoEncoder::Software);
+        assert!(capabilities.resolve(VideoEncoder::Software) == VideoEncoder::Software);
+        // Nothing detected yet, or no ffmpeg at all.
+        assert!(Capabilities::default().resolve(VideoEncoder::Amf) == VideoEncoder::Software);
+        assert!(capabilities.can_decode(VideoEncoder::Vaapi));
+        assert!(!capabilities.can_decode(VideoEncoder::Amf));
+        assert!(!capabilities.can_decode(VideoEncoder::Software));
+    }
+
+    #[test]
+    fn software_adds_no_encoder_args() {
+        assert!(VideoEncoder::Software.input_args(true).is_empty());
+        assert!(VideoEncoder::Software.output_args().is_empty());
+        assert!(VideoEncoder::Software.upload_filter().is_none());
+        assert_eq!(VideoEncoder::Vaapi.input_args(true), ["-vaapi_device", VAAPI_DEVICE, "-hwaccel", "vaapi"]);
+    }
+}
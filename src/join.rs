@@ -0,0 +1,279 @@
+// Joining several videos back to back, each one optionally trimmed first.
+//
+// When every clip has the same codecs, size, frame rate and timebase, the concat demuxer copies them into one file
+// without re-encoding. Anything else goes through the concat filter instead, with every clip scaled and padded to the
+// first one's size and frame rate and the audio resampled to match, which means a re-encode.
+
+use std::{fs, path::Path};
+
+use crate::{
+    cache, num_to_time,
+    probe::{self, VideoInfo},
+};
+
+// What the audio of every clip is brought to when re-encoding.
+const SAMPLE_RATE: u32 = 48000;
+
+#[derive(Clone)]
+pub struct JoinClip {
+    pub path: String,
+    pub info: VideoInfo,
+    pub start: f32,
+    // None runs to the end of the clip.
+    pub end: Option<f32>,
+}
+
+impl JoinClip {
+    // None if ffprobe can't find a video stream in `path`.
+    pub fn open(path: String) -> Option<Self> {
+        let info = probe::probe(&path)?;
+        Some(Self {
+            path,
+            info,
+            start: 0.0,
+            end: None,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        Path::new(&self.path)
+            .file_name()
+            .map_or_else(|| self.path.clone(), |n| n.to_string_lossy().into_owned())
+    }
+
+    pub fn is_trimmed(&self) -> bool {
+        self.start > 0.0 || self.end.is_some()
+    }
+
+    pub fn length(&self) -> f32 {
+        self.end.unwrap_or(self.info.duration) - self.start
+    }
+}
+
+// Reasons the clips can't be copied back to back, empty if they can. Everything is compared with the first clip.
+pub fn copy_problems(clips: &[JoinClip]) -> Vec<String> {
+    let Some(first) = clips.first() else {
+        return vec![];
+    };
+    let a = &first.info;
+    let mut problems = vec![];
+    for clip in &clips[1..] {
+        let (b, name) = (&clip.info, clip.name());
+        if a.video_codec != b.video_codec {
+            problems.push(format!("{name} is {} video, the first clip is {}", b.video_codec, a.video_codec));
+        }
+        if (a.width, a.height) != (b.width, b.height) {
+            problems.push(format!("{name} is {}x{}, the first clip is {}x{}", b.width, b.height, a.width, a.height));
+        }
+        if (a.frame_rate - b.frame_rate).abs() > 0.01 {
+            problems.push(format!("{name} is {:.3} fps, the first clip is {:.3} fps", b.frame_rate, a.frame_rate));
+        }
+        if a.time_base != b.time_base {
+            problems.push(format!("{name} has a timebase of {}, the first clip {}", b.time_base, a.time_base));
+        }
+        if a.pixel_format != b.pixel_format {
+            problems.push(format!("{name} is stored as {}, the first clip as {}", b.pixel_format, a.pixel_format));
+        }
+        if a.rotation != b.rotation || a.sample_aspect_ratio != b.sample_aspect_ratio {
+            problems.push(format!("{name} is shown at a different rotation or pixel shape"));
+        }
+        match (a.audio_streams.first(), b.audio_streams.first()) {
+            (Some(x), Some(y)) => {
+                if x.codec != y.codec {
+                    problems.push(format!("{name} has {} audio, the first clip {}", y.codec, x.codec));
+                }
+                if (x.sample_rate, x.channels) != (y.sample_rate, y.channels) {
+                    problems.push(format!(
+                        "{name} has {} Hz {}ch audio, the first clip {} Hz {}ch",
+                        y.sample_rate, y.channels, x.sample_rate, x.channels
+                    ));
+                }
+            }
+            (None, None) => {}
+            (Some(_), None) => problems.push(format!("{name} has no audio, the first clip does")),
+            (None, Some(_)) => problems.push(format!("{name} has audio, the first clip doesn't")),
+        }
+    }
+    problems
+}
+
+// Size and frame rate everything is brought to when re-encoding, taken from the first clip as it's displayed.
+pub fn target_format(clips: &[JoinClip]) -> Option<([u32; 2], f32)> {
+    let info = &clips.first()?.info;
+    let [width, height] = info.display_size();
+    Some((probe::fit_size(info.display_aspect(), width as u32, height as u32), info.frame_rate))
+}
+
+// Quoted strings in an ffconcat list can't contain a quote, so each one closes the string, adds an escaped quote
+// and opens it again.
+fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', "'\\''"))
+}
+
+// The list the concat demuxer reads. inpoint/outpoint trim each clip, and since it's a copy, a trimmed clip starts
+// from the keyframe before its inpoint.
+fn concat_list(clips: &[JoinClip]) -> String {
+    let mut list = String::from("ffconcat version 1.0\n");
+    for clip in clips {
+        list.push_str(&format!("file {}\n", quote(&clip.path)));
+        if clip.start > 0.0 {
+            list.push_str(&format!("inpoint {}\n", clip.start));
+        }
+        if let Some(end) = clip.end {
+            list.push_str(&format!("outpoint {end}\n"));
+        }
+    }
+    list
+}
+
+// Arguments for joining without re-encoding. None if the list file couldn't be written. Every call writes a new
+// list, so jobs already in the queue keep their own.
+pub fn copy_args(clips: &[JoinClip], output: &str, overwrite: bool) -> Option<Vec<String>> {
+    let list_path = cache::job_file("ffconcat")?;
+    fs::write(&list_path, concat_list(clips)).ok()?;
+
+    let mut args: Vec<String> = vec![];
+    if overwrite {
+        args.push("-y".into());
+    }
+    // -safe 0 allows absolute paths in the list.
+    args.extend(["-f", "concat", "-safe", "0", "-i"].map(String::from));
+    args.push(list_path.display().to_string());
+    args.extend(["-map", "0:v:0", "-map", "0:a?", "-c", "copy"].map(String::from));
+    args.push(output.to_owned());
+    Some(args)
+}
+
+// Arguments for joining through the concat filter. Clips without audio get silence so the tracks stay in step.
+// None if there are no clips.
+pub fn reencode_args(clips: &[JoinClip], output: &str, overwrite: bool) -> Option<Vec<String>> {
+    let ([width, height], frame_rate) = target_format(clips)?;
+    let mut args: Vec<String> = vec![];
+    if overwrite {
+        args.push("-y".into());
+    }
+    for clip in clips {
+        args.extend(["-ss".into(), num_to_time(clip.start)]);
+        if let Some(end) = clip.end {
+            args.extend(["-to".into(), num_to_time(end)]);
+        }
+        args.extend(["-i".into(), clip.path.clone()]);
+    }
+    let with_audio = clips.iter().any(|clip| !clip.info.audio_streams.is_empty());
+    let mut graph = String::new();
+    let mut inputs = String::new();
+    for (i, clip) in clips.iter().enumerate() {
+        graph.push_str(&format!(
+            "[{i}:v:0]scale={width}:{height}:force_original_aspect_ratio=decrease,\
+             pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={frame_rate},format=yuv420p[v{i}];"
+        ));
+        inputs.push_str(&format!("[v{i}]"));
+        if !with_audio {
+            continue;
+        }
+        if clip.info.audio_streams.is_empty() {
+            graph.push_str(&format!("anullsrc=r={SAMPLE_RATE}:cl=stereo,atrim=duration={}[a{i}];", clip.length()));
+        } else {
+            graph.push_str(&format!(
+                "[{i}:a:0]aresample={SAMPLE_RATE},aformat=sample_fmts=fltp:channel_layouts=stereo[a{i}];"
+            ));
+        }
+        inputs.push_str(&format!("[a{i}]"));
+    }
+    graph.push_str(&format!("{inputs}concat=n={}:v=1:a={}[v]", clips.len(), with_audio as u8));
+    if with_audio {
+        graph.push_str("[a]");
+    }
+    args.extend(["-filter_complex".into(), graph, "-map".into(), "[v]".into()]);
+    if with_audio {
+        args.extend(["-map".into(), "[a]".into()]);
+    }
+    args.push(output.to_owned());
+    Some(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::tests::{dvd, phone};
+
+    fn clip(path: &str, info: VideoInfo, start: f32, end: Option<f32>) -> JoinClip {
+        JoinClip {
+            path: path.to_owned(),
+            info,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn quotes_in_list_paths() {
+        assert_eq!(quote("it's.mp4"), "'it'\\''s.mp4'");
+        assert_eq!(quote("/videos/a b.mp4"), "'/videos/a b.mp4'");
+    }
+
+    #[test]
+    fn list_only_trims_what_was_trimmed() {
+        let clips = [
+            clip("/videos/a.mp4", phone(), 0.0, None),
+            clip("/videos/b.mp4", phone(), 5.0, Some(20.5)),
+            clip("/videos/c.mp4", phone(), 0.0, Some(3.0)),
+        ];
+        assert_eq!(
+            concat_list(&clips),
+            "ffconcat version 1.0
+file '/videos/a.mp4'
+file '/videos/b.mp4'
+inpoint 5
+outpoint 20.5
+file '/videos/c.mp4'
+outpoint 3
+"
+        );
+    }
+
+    #[test]
+    fn mismatches_stop_a_copy() {
+        assert!(copy_problems(&[]).is_empty());
+        assert!(copy_problems(&[clip("a.mp4", phone(), 0.0, None), clip("b.mp4", phone(), 2.0, None)]).is_empty());
+        let problems = copy_problems(&[clip("a.mp4", phone(), 0.0, None), clip("b.mkv", dvd(), 0.0, None)]);
+        for expected in [
+            "b.mkv is mpeg2video video, the first clip is hevc",
+            "b.mkv is 720x480, the first clip is 1920x1080",
+            "b.mkv is 29.970 fps, the first clip is 30.000 fps",
+            "b.mkv has 48000 Hz 6ch audio, the first clip 44100 Hz 2ch",
+        ] {
+            assert!(problems.iter().any(|p| p == expected), "{expected} missing from {problems:?}");
+        }
+    }
+
+    #[test]
+    fn silence_for_clips_without_audio() {
+        let mut silent = dvd();
+        silent.audio_streams.clear();
+        let clips = [clip("a.mp4", phone(), 1.0, Some(4.0)), clip("b.mkv", silent, 0.0, Some(15.0))];
+        let args = reencode_args(&clips, "out.mp4", false).unwrap();
+        let graph = &args[args.iter().position(|a| a == "-filter_complex").unwrap() + 1];
+        // The portrait phone clip sets the size everything is brought to.
+        assert!(
+            graph.starts_with("[0:v:0]scale=1080:1920:force_original_aspect_ratio=decrease,"),
+            "{graph}"
+        );
+        assert!(
+            graph.contains("[0:a:0]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[a0];"),
+            "{graph}"
+        );
+        assert!(graph.contains("anullsrc=r=48000:cl=stereo,atrim=duration=15[a1];"), "{graph}");
+        assert!(graph.ends_with("[v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"), "{graph}");
+        assert_eq!(
+            args[..8],
+            ["-ss", "00:00:01.00", "-to", "00:00:04.00", "-i", "a.mp4", "-ss", "00:00:00.00"]
+        );
+        assert_eq!(args[args.len() - 5..], ["-map", "[v]", "-map", "[a]", "out.mp4"]);
+    }
+
+    #[test]
+    fn nothing_to_join() {
+        assert!(reencode_args(&[], "out.mp4", true).is_none());
+    }
+}
@@ -1,29 +1,87 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod audio;
+mod cache;
+mod chapters;
+mod contactsheet;
+mod container;
+mod crop;
+mod deadair;
+mod filmstrip;
+mod frames;
+mod history;
+mod hwaccel;
+mod join;
+mod metadata;
+mod overlay;
+mod playback;
+mod probe;
+mod queue;
+mod reframe;
+mod scenes;
+mod settings;
+mod shortcuts;
+mod split;
+mod subtitles;
+mod timeline;
+mod trim;
+mod waveform;
+
 use std::{
     env,
-    os::windows::process::CommandExt,
-    process::Command, vec,
+    process::Command,
+    sync::mpsc::Receiver,
+    vec,
 };
 
+use audio::AudioSettings;
+use chapters::{ChapterSource, Marker};
+use contactsheet::SheetLayout;
+use crop::Crop;
+use deadair::{DetectOptions, Region};
 use eframe::egui::{self, pos2, vec2, Align2, Color32, ColorImage};
 use egui_toast::Toasts;
+use filmstrip::Filmstrip;
+use frames::{FrameSource, StillFormat};
+use history::{History, TrimState};
+use hwaccel::{Capabilities, VideoEncoder};
+use join::JoinClip;
+use overlay::Overlay;
+use playback::Playback;
+use probe::{Chapter, VideoInfo};
+use queue::{ExportQueue, JobStatus};
+use reframe::{Reframe, ReframeMode};
+use settings::Settings;
+use split::{SplitMode, SplitSettings};
+use subtitles::SubtitleSettings;
+use timeline::{TimelineView, TrimTimeline};
+use trim::{Fades, KeepAll, Speed, TrimBuilder};
 
 // https://stackoverflow.com/a/75292572
+#[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+// Preview frames are pulled at the largest size with the video's display aspect ratio that fits in this square,
+// then scaled to whatever space the window has for them.
+const PREVIEW_FRAME_BOX: u32 = 640;
+
+// Heights the export can be scaled down to. The width follows the aspect ratio.
+const OUTPUT_HEIGHTS: [u32; 4] = [1080, 720, 480, 360];
+
+// Height of the preview in the Overlays window.
+const OVERLAY_PREVIEW_HEIGHT: u32 = 270;
+
 // TODO:
 // - make multithreaded
 // - windows right click open with
 // - settings window
-// - scrubbers on same y (maybe use https://docs.rs/egui/latest/egui/struct.Response.html#method.with_new_rect)
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_icon(eframe::icon_data::from_png_bytes(&include_bytes!("../assets/icon.png")[..]).unwrap())
-            .with_inner_size([656.0, 440.0])
-            .with_resizable(false)
+            .with_inner_size([960.0, 640.0])
+            .with_min_inner_size([656.0, 480.0])
             .with_drag_and_drop(true),
         ..Default::default()
     };
@@ -32,7 +90,11 @@ fn main() -> Result<(), eframe::Error> {
         options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Box::<QuickTrim>::default()
+            Box::new(QuickTrim {
+                settings: Settings::load(cc.storage),
+                hardware_detector: Some(hwaccel::detect(&cc.egui_ctx)),
+                ..Default::default()
+            })
         }),
     )
 }
@@ -56,12 +118,101 @@ pub struct QuickTrim {
     ffmpeg_gen_output: Option<String>,
     opened_using_open_with_windows: bool,
     args: Option<Vec<String>>,
-    preview_has_loaded: bool,
     preview_image_start_handle: Option<egui::TextureHandle>,
     preview_image_end_handle: Option<egui::TextureHandle>,
     keep_existing_trim_data: bool,
     dropped_file: bool,
-    preview_image_dimensions: Vec<i32>,
+    video_info: Option<VideoInfo>,
+    history: History,
+    playhead: f32,
+    shuttle_speed: f32,
+    show_cheat_sheet: bool,
+    show_playback: bool,
+    playback: Option<Playback>,
+    playback_texture: Option<egui::TextureHandle>,
+    // The playhead as playback last left it, so a seek by the user can be told apart.
+    playback_playhead: f32,
+    loop_selection: bool,
+    waveform: Option<waveform::Peaks>,
+    waveform_loader: Option<Receiver<Option<waveform::Peaks>>>,
+    filmstrip: Option<Filmstrip>,
+    filmstrip_loader: Option<Receiver<Option<ColorImage>>>,
+    timeline_view: TimelineView,
+    queue: ExportQueue,
+    pending_trim: Option<u64>,
+    show_side_panel: bool,
+    side_panel_tab: SidePanelTab,
+    crop: Crop,
+    crop_aspect_lock: Option<f32>,
+    show_crop_editor: bool,
+    crop_detector: Option<Receiver<Option<Crop>>>,
+    output_height: Option<u32>,
+    reframe: Reframe,
+    show_reframe: bool,
+    reframe_mockup: Option<egui::TextureHandle>,
+    reframe_mockup_loader: Option<Receiver<Option<ColorImage>>>,
+    // What the current (or loading) mockup was rendered from, so it's only redone when something changes.
+    reframe_mockup_source: Option<(f32, Vec<String>)>,
+    audio: AudioSettings,
+    show_audio: bool,
+    keep_all_streams: bool,
+    fades: Fades,
+    speed: Speed,
+    subtitles: SubtitleSettings,
+    show_subtitles: bool,
+    overlays: Vec<Overlay>,
+    show_overlays: bool,
+    selected_overlay: usize,
+    overlay_preset_name: String,
+    overlay_preview: Option<egui::TextureHandle>,
+    overlay_preview_loader: Option<Receiver<Option<ColorImage>>>,
+    overlay_preview_source: Option<(f32, Vec<String>)>,
+    settings: Settings,
+    show_settings: bool,
+    // None until detection has finished.
+    hardware: Option<Capabilities>,
+    hardware_detector: Option<Receiver<Capabilities>>,
+    // None until the scene cuts have been looked for.
+    scene_cuts: Option<Vec<f32>>,
+    scene_detector: Option<Receiver<Option<Vec<f32>>>>,
+    scene_sensitivity: f32,
+    snap_to_scenes: bool,
+    // None until the video has been looked through.
+    dead_air: Option<Vec<Region>>,
+    dead_air_detector: Option<Receiver<Option<Vec<Region>>>>,
+    dead_air_options: DetectOptions,
+    remove_silences: bool,
+    // Only silences at least this long are cut out.
+    min_silence: f32,
+    // Kept in time order.
+    markers: Vec<Marker>,
+    chapter_source: ChapterSource,
+    split: SplitSettings,
+    // Only loaded once a split needs them, None until then.
+    keyframes: Option<Vec<f32>>,
+    keyframe_loader: Option<Receiver<Option<Vec<f32>>>>,
+    show_join: bool,
+    join_clips: Vec<JoinClip>,
+    join_output: Option<String>,
+    join_reencode: bool,
+    // The clip that's open in the scrubber, which takes on the trim set there.
+    join_editing: Option<usize>,
+    show_frames: bool,
+    frame_source: FrameSource,
+    // 1 exports every frame of the trim.
+    frame_step: u32,
+    sequence_format: StillFormat,
+    clipboard_copier: Option<Receiver<Result<(), String>>>,
+    contact_sheet: SheetLayout,
+    toasts: Toasts,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SidePanelTab {
+    Info,
+    Chapters,
+    Queue,
+    Logs,
 }
 
 impl Default for QuickTrim {
@@ -83,19 +234,1606 @@ impl Default for QuickTrim {
             ffmpeg_gen_output: None,
             opened_using_open_with_windows: false,
             args: None,
-            preview_has_loaded: false,
             preview_image_start_handle: None,
             preview_image_end_handle: None,
             keep_existing_trim_data: false,
             dropped_file: false,
-            preview_image_dimensions: vec![],
+            video_info: None,
+            history: History::default(),
+            playhead: 0.0,
+            shuttle_speed: 0.0,
+            show_cheat_sheet: false,
+            show_playback: false,
+            playback: None,
+            playback_texture: None,
+            playback_playhead: 0.0,
+            loop_selection: false,
+            waveform: None,
+            waveform_loader: None,
+            filmstrip: None,
+            filmstrip_loader: None,
+            timeline_view: TimelineView::default(),
+            queue: ExportQueue::default(),
+            pending_trim: None,
+            show_side_panel: true,
+            side_panel_tab: SidePanelTab::Info,
+            crop: Crop::FULL,
+            crop_aspect_lock: None,
+            show_crop_editor: false,
+            crop_detector: None,
+            output_height: None,
+            reframe: Reframe::default(),
+            show_reframe: false,
+            reframe_mockup: None,
+            reframe_mockup_loader: None,
+            reframe_mockup_source: None,
+            audio: AudioSettings::default(),
+            show_audio: false,
+            keep_all_streams: false,
+            fades: Fades::default(),
+            speed: Speed::default(),
+            subtitles: SubtitleSettings::default(),
+            show_subtitles: false,
+            overlays: vec![],
+            show_overlays: false,
+            selected_overlay: 0,
+            overlay_preset_name: String::new(),
+            overlay_preview: None,
+            overlay_preview_loader: None,
+            overlay_preview_source: None,
+            settings: Settings::default(),
+            show_settings: false,
+            hardware: None,
+            hardware_detector: None,
+            scene_cuts: None,
+            scene_detector: None,
+            scene_sensitivity: scenes::DEFAULT_SENSITIVITY,
+            snap_to_scenes: true,
+            dead_air: None,
+            dead_air_detector: None,
+            dead_air_options: DetectOptions { black: false, freeze: false },
+            remove_silences: false,
+            min_silence: 1.0,
+            markers: vec![],
+            chapter_source: ChapterSource::default(),
+            split: SplitSettings::default(),
+            keyframes: None,
+            keyframe_loader: None,
+            show_join: false,
+            join_clips: vec![],
+            join_output: None,
+            join_reencode: false,
+            join_editing: None,
+            show_frames: false,
+            frame_source: FrameSource::Playhead,
+            frame_step: 1,
+            sequence_format: StillFormat::Png,
+            clipboard_copier: None,
+            contact_sheet: SheetLayout::default(),
+            toasts: Toasts::new()
+                .anchor(Align2::RIGHT_BOTTOM, (-10.0, -10.0))
+                .direction(egui::Direction::BottomUp),
+        }
+    }
+}
+
+impl QuickTrim {
+    // Like "Refresh Data" used to be, but keeps the undo history so the reset itself can be undone,
+    // keeps any exports that are still running and leaves the side panel and settings as they were.
+    fn reset(&mut self) {
+        let previous = std::mem::take(self);
+        self.history = previous.history;
+        self.settings = previous.settings;
+        self.hardware = previous.hardware;
+        self.hardware_detector = previous.hardware_detector;
+        self.queue = previous.queue;
+        self.pending_trim = previous.pending_trim;
+        self.show_side_panel = previous.show_side_panel;
+        self.side_panel_tab = previous.side_panel_tab;
+    }
+
+    fn trim_state(&self) -> TrimState {
+        TrimState {
+            picked_path: self.picked_path.clone(),
+            start_trim: self.start_trim,
+            end_trim: self.end_trim,
+            trim_to_end: self.trim_to_end,
+            slow_trim: self.slow_trim,
+            overwrite: self.overwrite,
+            output_location: self.output_location.clone(),
+            crop: self.crop,
+            output_height: self.output_height,
+            reframe: self.reframe,
+            audio: self.audio.clone(),
+            keep_all_streams: self.keep_all_streams,
+            fades: self.fades,
+            speed: self.speed,
+            subtitles: self.subtitles.clone(),
+            overlays: self.overlays.clone(),
+            remove_silences: self.remove_silences,
+            min_silence: self.min_silence,
+            markers: self.markers.clone(),
+            chapter_source: self.chapter_source,
+            split: self.split,
+        }
+    }
+
+    fn restore_trim_state(&mut self, state: TrimState, ctx: &egui::Context) {
+        if state.picked_path != self.picked_path {
+            self.picked_path = state.picked_path.clone();
+            self.video_info = None;
+            if self.picked_path.is_some() {
+                analyze_picked_video(self, ctx);
+            } else {
+                self.scrubber_is_visible = false;
+                self.video_length = 0;
+                self.video_info = None;
+                self.preview_image_start_handle = None;
+                self.preview_image_end_handle = None;
+            }
+        }
+        let start_changed = state.start_trim != self.start_trim;
+        let end_changed = state.end_trim != self.end_trim;
+        self.start_trim = state.start_trim;
+        self.end_trim = state.end_trim;
+        self.trim_to_end = state.trim_to_end;
+        self.slow_trim = state.slow_trim;
+        self.overwrite = state.overwrite;
+        self.output_location = state.output_location;
+        self.crop = state.crop;
+        self.output_height = state.output_height;
+        self.reframe = state.reframe;
+        self.audio = state.audio;
+        self.keep_all_streams = state.keep_all_streams;
+        self.fades = state.fades;
+        self.speed = state.speed;
+        self.subtitles = state.subtitles;
+        self.overlays = state.overlays;
+        self.remove_silences = state.remove_silences;
+        self.min_silence = state.min_silence;
+        self.markers = state.markers;
+        self.chapter_source = state.chapter_source;
+        self.split = state.split;
+        if start_changed {
+            self.load_start_preview(ctx);
+        }
+        if end_changed {
+            self.load_end_preview(ctx);
+        }
+    }
+
+    fn display_aspect(&self) -> f32 {
+        self.video_info.as_ref().map_or(16.0 / 9.0, VideoInfo::display_aspect)
+    }
+
+    fn preview_frame_size(&self) -> [u32; 2] {
+        probe::fit_size(self.display_aspect(), PREVIEW_FRAME_BOX, PREVIEW_FRAME_BOX)
+    }
+
+    fn load_start_preview(&mut self, ctx: &egui::Context) {
+        if let Some(p) = &self.picked_path {
+            if let Some(d) = get_video_frame(p, &num_to_time(self.start_trim), self.preview_frame_size()) {
+                self.preview_image_start_handle = Some(ctx.load_texture("preview_start", d, Default::default()));
+            }
+        }
+    }
+
+    fn load_end_preview(&mut self, ctx: &egui::Context) {
+        if let Some(p) = &self.picked_path {
+            if let Some(d) = get_video_frame(p, &num_to_time(self.end_trim), self.preview_frame_size()) {
+                self.preview_image_end_handle = Some(ctx.load_texture("preview_end", d, Default::default()));
+            }
+        }
+    }
+
+    fn undo(&mut self, ctx: &egui::Context) {
+        if let Some(state) = self.history.undo() {
+            self.restore_trim_state(state, ctx);
+        }
+    }
+
+    fn redo(&mut self, ctx: &egui::Context) {
+        if let Some(state) = self.history.redo() {
+            self.restore_trim_state(state, ctx);
+        }
+    }
+
+    fn open_file_dialog(&mut self, ctx: &egui::Context) {
+        if let Some(path) = rfd::FileDialog::new()
+            .set_title("Open File to Trim")
+            .add_filter("Video File", &["mp4", "mov", "mkv"])
+            .pick_file()
+        {
+            self.picked_path = Some(path.display().to_string());
+            analyze_picked_video(self, ctx);
+        }
+    }
+
+    // Crop first so the scale applies to what's left of the frame. Reframing does its own scaling, and has to
+    // come last since it ends in a filter graph rather than a plain filter.
+    fn video_filters(&self) -> Vec<String> {
+        self.video_filters_sized(Reframe::output_size(self.output_height))
+    }
+
+    fn video_filters_sized(&self, reframe_size: [u32; 2]) -> Vec<String> {
+        let mut filters = vec![];
+        if let (false, Some(info)) = (self.crop.is_full(), &self.video_info) {
+            filters.push(self.crop.filter(info));
+        }
+        if let Some(reframe) = self.reframe.filter(reframe_size) {
+            filters.push(reframe);
+        } else if let Some(height) = self.output_height {
+            filters.push(format!("scale=-2:{height}"));
+        }
+        // Overlays go on last so they're placed on the frame as it's exported.
+        filters.extend(self.overlays.iter().enumerate().filter_map(|(index, overlay)| overlay.filter(index)));
+        filters
+    }
+
+    // Length of the exported clip once the speed change is taken into account.
+    fn output_length(&self) -> f32 {
+        let length = match self.segments() {
+            Some(segments) => segments.iter().map(|(from, to)| to - from).sum(),
+            None => self.trim_end() - self.start_trim,
+        };
+        self.speed.output_length(length.max(0.0))
+    }
+
+    fn trim_end(&self) -> f32 {
+        if self.trim_to_end {
+            self.duration()
+        } else {
+            self.end_trim
+        }
+    }
+
+    // The parts of the trim that are kept when silences are cut out, None if nothing is cut.
+    fn segments(&self) -> Option<Vec<(f32, f32)>> {
+        self.segments_between(self.start_trim, self.trim_end())
+    }
+
+    fn segments_between(&self, start: f32, end: f32) -> Option<Vec<(f32, f32)>> {
+        let regions = self.dead_air.as_ref().filter(|_| self.remove_silences)?;
+        let segments = deadair::keep_segments(regions, start, end, self.min_silence);
+        (!segments.is_empty() && segments != [(start, end)]).then_some(segments)
+    }
+
+    fn duration(&self) -> f32 {
+        self.video_info.as_ref().map_or(self.video_length as f32, |info| info.duration)
+    }
+
+    // The chapters that go into the export, on the source's timeline.
+    fn export_chapters(&self) -> Vec<Chapter> {
+        match (self.chapter_source, &self.video_info) {
+            (ChapterSource::Original, Some(info)) => info.chapters.clone(),
+            (ChapterSource::Markers, _) => chapters::marker_chapters(&self.markers, self.duration()),
+            _ => vec![],
+        }
+    }
+
+    // "Keep everything" only works when nothing gets re-encoded.
+    fn is_stream_copy(&self) -> bool {
+        !self.slow_trim
+            && self.video_filters().is_empty()
+            && !self.fades.any()
+            && !self.speed.is_changed()
+            && !self.audio.plan().needs_reencode()
+            && !self.burns_in_subtitles()
+            && self.segments().is_none()
+    }
+
+    fn burns_in_subtitles(&self) -> bool {
+        self.video_info
+            .as_ref()
+            .is_some_and(|info| self.subtitles.burns_in(&info.subtitle_streams))
+    }
+
+    fn toast(&mut self, kind: egui_toast::ToastKind, text: impl Into<egui::WidgetText>) {
+        self.toasts.add(egui_toast::Toast {
+            text: text.into(),
+            kind,
+            options: egui_toast::ToastOptions::default().duration_in_seconds(4.0).show_progress(true),
+        });
+    }
+
+    fn keeps_all_streams(&self) -> bool {
+        self.keep_all_streams && self.is_stream_copy() && self.video_info.is_some()
+    }
+
+    fn trim(&mut self) {
+        if self.picked_path.is_none() {
+            self.toast(
+                egui_toast::ToastKind::Error,
+                "You need to provide the path to the video you want to trim!",
+            );
+        }
+        if self.output_location.is_none() {
+            self.toast(egui_toast::ToastKind::Error, "You need to provide the path to the output file!");
+        }
+        // Having these as separate "if" statements lets multiple toasts appear.
+        if self.picked_path.is_some() && self.output_location.is_some() {
+            self.trim_can_continue = true;
+        }
+
+        if self.trim_can_continue {
+            let output = self.output_location.clone().unwrap();
+            self.warn_dropped_streams(&output);
+            let end = if self.trim_to_end { None } else { Some(self.end_trim) };
+            self.pending_trim = Some(self.queue_export(self.start_trim, end, &output));
+            self.side_panel_tab = SidePanelTab::Queue;
+        }
+    }
+
+    fn split_by_chapters(&mut self) {
+        let parts = self.export_chapters().into_iter().map(|c| (c.start, c.end, c.title)).collect();
+        self.queue_parts(parts);
+    }
+
+    // The parts the split settings cut the trim into. Copies are cut on keyframes once they've been read.
+    fn split_ranges(&self) -> Vec<(f32, f32)> {
+        let keyframes = self.keyframes.as_deref().filter(|_| self.is_stream_copy());
+        self.split.ranges(self.start_trim, self.trim_end(), keyframes)
+    }
+
+    // Queues a trim per part, each to its own file next to the output and with the same settings as a normal trim.
+    fn queue_parts(&mut self, parts: Vec<(f32, f32, Option<String>)>) {
+        let (Some(_), Some(output)) = (&self.picked_path, self.output_location.clone()) else {
+            self.toast(
+                egui_toast::ToastKind::Error,
+                "You need to provide the video and the output file before splitting!",
+            );
+            return;
+        };
+        self.warn_dropped_streams(&output);
+        for (index, (start, end, title)) in parts.iter().enumerate() {
+            let part = split::part_path(&output, &self.settings.split_template, index + 1, parts.len(), title.as_deref());
+            self.queue_export(*start, Some(*end), &part);
+        }
+        self.side_panel_tab = SidePanelTab::Queue;
+    }
+
+    fn warn_dropped_streams(&mut self, output: &str) {
+        let Some(info) = self.video_info.as_ref().filter(|_| self.keeps_all_streams()) else {
+            return;
+        };
+        let dropped = container::unsupported_streams(&info.streams, output);
+        if !dropped.is_empty() {
+            self.toast(
+                egui_toast::ToastKind::Warning,
+                format!("Leaving out {} stream(s) the output format can't hold.", dropped.len()),
+            );
+        }
+    }
+
+    // Queues start..end of the picked video to `output` with the current settings, and returns the job's id.
+    fn queue_export(&mut self, start: f32, end: Option<f32>, output: &str) -> u64 {
+        let path = self.picked_path.clone().unwrap();
+        let clip_end = end.unwrap_or(self.duration());
+        let builder = self
+            .video_filters()
+            .into_iter()
+            .fold(TrimBuilder::new(&path, output), TrimBuilder::video_filter)
+            .range(start, end)
+            .reencode(self.slow_trim)
+            .overwrite(self.overwrite)
+            .fades(self.fades, clip_end - start)
+            .speed(self.speed);
+        // Chapter times are moved onto the clip's timeline, pulled back over cut silences, stretched along with
+        // a speed change and mirrored when it plays backwards.
+        let mut chapters = metadata::clamp_chapters(&self.export_chapters(), start, clip_end);
+        let mut length = clip_end - start;
+        let builder = match self.segments_between(start, clip_end) {
+            Some(segments) => {
+                chapters = metadata::remove_cuts(chapters, start, &segments);
+                length = segments.iter().map(|(from, to)| to - from).sum();
+                builder.segments(segments)
+            }
+            None => builder,
+        };
+        let mut chapters: Vec<Chapter> = chapters
+            .into_iter()
+            .map(|chapter| Chapter {
+                start: self.speed.output_length(chapter.start),
+                end: self.speed.output_length(chapter.end),
+                ..chapter
+            })
+            .collect();
+        if self.speed.reverse {
+            chapters = metadata::mirror_chapters(chapters, self.speed.output_length(length));
+        }
+        let builder = builder.chapters(metadata::write_chapters(&chapters));
+        // Without probe info there's nothing to pick from, so ffmpeg's default mapping is used.
+        let builder = match &self.video_info {
+            Some(info) if self.keeps_all_streams() => {
+                let dropped = container::unsupported_streams(&info.streams, output);
+                builder.keep_all_streams(KeepAll {
+                    dropped: dropped.iter().map(|(stream, _)| stream.index).collect(),
+                })
+            }
+            Some(info) => match self.subtitles.plan(&info.subtitle_streams) {
+                Some(plan) => builder.audio(self.audio.plan()).subtitles(plan),
+                None => builder.audio(self.audio.plan()),
+            },
+            None => builder,
+        };
+        // A hardware export that fails is redone in software.
+        let encoder = self
+            .hardware
+            .as_ref()
+            .map_or(VideoEncoder::Software, |h| h.resolve(self.settings.encoder));
+        let decode = self.settings.hardware_decode && self.hardware.as_ref().is_some_and(|h| h.can_decode(encoder));
+        let builder = builder.video_encoder(encoder, decode);
+        let args = builder.build();
+        let fallback = builder
+            .uses_hardware()
+            .then(|| builder.video_encoder(VideoEncoder::Software, false).build());
+        let id = self.queue.push_with_fallback(format!("Trim to {output}"), args, fallback);
+        // The .srt is a separate run, so it doesn't hold up the trim and works whatever the output format is.
+        let sidecar = self.video_info.as_ref().and_then(|info| self.subtitles.plan(&info.subtitle_streams));
+        if let Some(plan) = sidecar.filter(|p| self.subtitles.sidecar && p.is_text() && !self.keeps_all_streams()) {
+            let srt = subtitles::sidecar_path(output);
+            let args = subtitles::sidecar_args(&path, &plan.source, start, end, &srt, self.overwrite);
+            self.queue.push(format!("Subtitles to {srt}"), args);
+        }
+        id
+    }
+
+    // Sets the trim to start..end and refreshes the previews, for jumping to a chapter.
+    fn trim_to_range(&mut self, ctx: &egui::Context, start: f32, end: f32) {
+        self.start_trim = start;
+        self.end_trim = end;
+        self.trim_to_end = end >= self.duration();
+        self.load_start_preview(ctx);
+        self.load_end_preview(ctx);
+    }
+
+    fn update_queue(&mut self, ctx: &egui::Context) {
+        for id in self.queue.poll() {
+            if Some(id) != self.pending_trim {
+                continue;
+            }
+            self.pending_trim = None;
+            let Some(job) = self.queue.get(id) else {
+                continue;
+            };
+            if !self.ffmpeg_gen_output_made {
+                self.ffmpeg_gen_output_made = true;
+                self.ffmpeg_gen_output = Some(job.log.clone());
+            }
+            if job.status == (JobStatus::Finished { success: true }) {
+                self.trim_finished = true;
+                if job.fell_back {
+                    self.toast(
+                        egui_toast::ToastKind::Warning,
+                        "The hardware encoder failed, so the clip was encoded in software. The logs say why.",
+                    );
+                }
+            } else {
+                self.toast(egui_toast::ToastKind::Error, "Trimming failed! Check the logs for what ffmpeg said.");
+                self.side_panel_tab = SidePanelTab::Logs;
+            }
+        }
+        if self.queue.is_busy() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+    }
+
+    // Length of one frame in seconds, used for frame stepping.
+    fn frame_step(&self) -> f32 {
+        match &self.video_info {
+            Some(info) => 1.0 / info.frame_rate,
+            None => 1.0 / 30.0,
+        }
+    }
+
+    fn set_playhead(&mut self, time: f32) {
+        self.playhead = time.clamp(0.0, self.video_length as f32);
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Global shortcuts use a modifier, so they are safe to handle while typing.
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::OPEN_FILE)) {
+            self.open_file_dialog(ctx);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::TRIM)) {
+            self.trim();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::CHEAT_SHEET)) {
+            self.show_cheat_sheet = !self.show_cheat_sheet;
+        }
+
+        // Everything else would get in the way of text fields (and their own undo) while they are being edited.
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        // Redo first, otherwise Ctrl+Shift+Z would also match the undo shortcut.
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::REDO)) {
+            self.redo(ctx);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::UNDO)) {
+            self.undo(ctx);
+        }
+
+        if self.picked_path.is_none() {
+            return;
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::MARK_IN)) {
+            self.start_trim = self.playhead;
+            if self.end_trim < self.start_trim {
+                self.end_trim = self.start_trim;
+                self.load_end_preview(ctx);
+            }
+            self.load_start_preview(ctx);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::MARK_OUT)) {
+            self.end_trim = self.playhead;
+            self.trim_to_end = false;
+            if self.start_trim > self.end_trim {
+                self.start_trim = self.end_trim;
+                self.load_start_preview(ctx);
+            }
+            self.load_end_preview(ctx);
+        }
+        // The marker is named in the Chapters tab, so typing doesn't get in the way of scrubbing.
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::ADD_MARKER)) {
+            chapters::add_marker(&mut self.markers, self.playhead);
+            self.side_panel_tab = SidePanelTab::Chapters;
+        }
+
+        // Shift+arrow before plain arrow, same reason as redo/undo.
+        let (second_back, second_forward) = ctx.input_mut(|i| {
+            (
+                i.consume_shortcut(&shortcuts::SECOND_BACK),
+                i.consume_shortcut(&shortcuts::SECOND_FORWARD),
+            )
+        });
+        let (frame_back, frame_forward) =
+            ctx.input_mut(|i| (i.consume_shortcut(&shortcuts::FRAME_BACK), i.consume_shortcut(&shortcuts::FRAME_FORWARD)));
+        if second_back {
+            self.set_playhead(self.playhead - 1.0);
+        }
+        if second_forward {
+            self.set_playhead(self.playhead + 1.0);
+        }
+        if frame_back {
+            self.set_playhead(self.playhead - self.frame_step());
+        }
+        if frame_forward {
+            self.set_playhead(self.playhead + self.frame_step());
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::JUMP_START)) {
+            self.set_playhead(0.0);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::JUMP_END)) {
+            self.set_playhead(self.video_length as f32);
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::PLAY_PAUSE)) {
+            self.toggle_playback(ctx);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::SHUTTLE_REVERSE)) {
+            self.stop_playback();
+            self.shuttle_speed = if self.shuttle_speed < 0.0 {
+                (self.shuttle_speed * 2.0).max(-shortcuts::MAX_SHUTTLE_SPEED)
+            } else {
+                -1.0
+            };
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::SHUTTLE_STOP)) {
+            self.stop_playback();
+            self.shuttle_speed = 0.0;
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcuts::SHUTTLE_FORWARD)) {
+            self.stop_playback();
+            self.shuttle_speed = if self.shuttle_speed > 0.0 {
+                (self.shuttle_speed * 2.0).min(shortcuts::MAX_SHUTTLE_SPEED)
+            } else {
+                1.0
+            };
+        }
+    }
+
+    // Playback frames are the same size as the still previews.
+    fn playback_size(&self) -> [usize; 2] {
+        let [width, height] = self.preview_frame_size();
+        [width as usize, height as usize]
+    }
+
+    fn start_playback(&mut self, ctx: &egui::Context, from: f32, stop_at: Option<f32>) {
+        let Some(path) = &self.picked_path else {
+            return;
+        };
+        let frame_rate = self.video_info.as_ref().map_or(30.0, |info| info.frame_rate);
+        let mut playback = Playback::start(ctx, path, from, self.playback_size(), frame_rate);
+        playback.stop_at = stop_at;
+        self.playback = Some(playback);
+        self.playhead = from;
+        self.playback_playhead = from;
+        self.shuttle_speed = 0.0;
+        self.show_playback = true;
+    }
+
+    fn stop_playback(&mut self) {
+        self.playback = None;
+    }
+
+    fn toggle_playback(&mut self, ctx: &egui::Context) {
+        if self.playback.is_some() {
+            self.stop_playback();
+        } else {
+            let from = if self.playhead >= self.video_length as f32 { 0.0 } else { self.playhead };
+            self.start_playback(ctx, from, None);
+        }
+    }
+
+    fn play_selection(&mut self, ctx: &egui::Context) {
+        self.start_playback(ctx, self.start_trim, Some(self.end_trim));
+    }
+
+    fn update_playback(&mut self, ctx: &egui::Context) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        // The playhead was moved by something else, so carry on playing from there
+        // (or pause while it is being dragged around the timeline).
+        if self.playhead != self.playback_playhead {
+            if ctx.input(|i| i.pointer.any_down()) {
+                self.stop_playback();
+            } else {
+                let stop_at = playback.stop_at.filter(|stop| *stop > self.playhead);
+                self.start_playback(ctx, self.playhead, stop_at);
+            }
+            return;
+        }
+        if let Some(image) = playback.poll() {
+            match &mut self.playback_texture {
+                Some(texture) => texture.set(image, Default::default()),
+                None => self.playback_texture = Some(ctx.load_texture("playback", image, Default::default())),
+            }
+        }
+        self.playhead = playback.position().min(self.video_length as f32);
+        if let Some(stop) = playback.stop_at {
+            self.playhead = self.playhead.min(stop);
+        }
+        self.playback_playhead = self.playhead;
+        self.timeline_view.follow(self.playhead, self.video_length as f32);
+        if playback.finished() {
+            if self.loop_selection && playback.stop_at.is_some() {
+                self.play_selection(ctx);
+            } else {
+                self.stop_playback();
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    // The frames at the start and end trim, side by side, each letterboxed into half the width and `max_height`.
+    fn preview_pair(&self, ui: &mut egui::Ui, max_height: f32) {
+        let slot_size = vec2(((ui.available_width() - 10.0) / 2.0).max(1.0), max_height.max(1.0));
+        let (rect, _) = ui.allocate_exact_size(vec2(ui.available_width(), slot_size.y), egui::Sense::hover());
+        let start_rect = egui::Rect::from_center_size(pos2(rect.center().x - slot_size.x / 2.0 - 5.0, rect.center().y), slot_size);
+        let end_rect = egui::Rect::from_center_size(pos2(rect.center().x + slot_size.x / 2.0 + 5.0, rect.center().y), slot_size);
+        for (texture, slot) in [(&self.preview_image_start_handle, start_rect), (&self.preview_image_end_handle, end_rect)] {
+            ui.painter().rect_filled(slot, 0.0, Color32::BLACK);
+            match texture {
+                Some(texture) => {
+                    let frame = letterbox(slot, self.display_aspect());
+                    egui::Image::new((texture.id(), texture.size_vec2())).paint_at(ui, frame);
+                    let to_screen = |crop: Crop| {
+                        egui::Rect::from_min_size(
+                            frame.min + vec2(crop.x, crop.y) * frame.size(),
+                            vec2(crop.width, crop.height) * frame.size(),
+                        )
+                    };
+                    if !self.crop.is_full() {
+                        ui.painter()
+                            .rect_stroke(to_screen(self.crop), 0.0, egui::Stroke::new(1.5, Color32::from_hex("#ffd24d").unwrap()));
+                    }
+                    if let Some(window) = self.reframe.window(self.crop, self.display_aspect()) {
+                        ui.painter()
+                            .rect_stroke(to_screen(window), 0.0, egui::Stroke::new(1.5, Color32::from_hex("#4dc3ff").unwrap()));
+                    }
+                }
+                None => {
+                    ui.put(slot, egui::Label::new("Unable to Load Frame Preview"));
+                }
+            }
+        }
+    }
+
+    fn reframe_window(&mut self, ctx: &egui::Context) {
+        if let Some(loader) = &self.reframe_mockup_loader {
+            if let Ok(image) = loader.try_recv() {
+                self.reframe_mockup = image.map(|image| ctx.load_texture("reframe_mockup", image, Default::default()));
+                self.reframe_mockup_loader = None;
+            }
+        }
+        if !self.show_reframe {
+            return;
+        }
+        // The mockup is redone once whatever it depends on has settled.
+        let source = (self.start_trim, self.video_filters_sized(reframe::MOCKUP_SIZE));
+        let settled = self.reframe_mockup_loader.is_none() && !ctx.input(|i| i.pointer.any_down());
+        if let (Some(path), true, true) = (&self.picked_path, self.reframe.is_on(), settled) {
+            if self.reframe_mockup_source.as_ref() != Some(&source) {
+                self.reframe_mockup_loader = Some(reframe::render_mockup(ctx, path, source.0, source.1.clone()));
+                self.reframe_mockup_source = Some(source);
+            }
+        }
+
+        let mut open = self.show_reframe;
+        egui::Window::new("Reframe").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.reframe.mode, ReframeMode::Off, "Off");
+                ui.selectable_value(&mut self.reframe.mode, ReframeMode::CenterCrop, "Centre Crop");
+                ui.selectable_value(&mut self.reframe.mode, ReframeMode::Positioned, "Positioned Crop");
+                ui.selectable_value(&mut self.reframe.mode, ReframeMode::BlurredBackground, "Blurred Background");
+            });
+            ui.add_enabled(
+                self.reframe.mode == ReframeMode::Positioned,
+                egui::Slider::new(&mut self.reframe.position, 0.0..=1.0)
+                    .show_value(false)
+                    .text("Position"),
+            );
+            let [width, height] = Reframe::output_size(self.output_height);
+            ui.label(format!("Exports at {width}x{height}"));
+            ui.separator();
+
+            let [width, height] = reframe::MOCKUP_SIZE;
+            let (rect, _) = ui.allocate_exact_size(vec2(width as f32, height as f32), egui::Sense::hover());
+            ui.painter().rect_filled(rect.expand(6.0), 12.0, Color32::from_gray(20));
+            match (&self.reframe_mockup, self.reframe.is_on()) {
+                (Some(texture), true) => {
+                    egui::Image::new((texture.id(), rect.size())).paint_at(ui, rect);
+                }
+                _ => {
+                    let text = if self.reframe.is_on() {
+                        "No mockup yet"
+                    } else {
+                        "Pick a mode to see a mockup"
+                    };
+                    ui.painter()
+                        .text(rect.center(), Align2::CENTER_CENTER, text, egui::FontId::default(), Color32::GRAY);
+                }
+            }
+            if self.reframe_mockup_loader.is_some() {
+                ui.put(egui::Rect::from_center_size(rect.center(), vec2(24.0, 24.0)), egui::Spinner::new());
+            }
+        });
+        self.show_reframe = open;
+    }
+
+    fn audio_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_audio;
+        egui::Window::new("Audio")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| match &self.video_info {
+                Some(info) => audio::audio_settings_ui(ui, &info.audio_streams, &mut self.audio),
+                None => {
+                    ui.label("Open a video to pick its audio tracks.");
+                }
+            });
+        self.show_audio = open;
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        if let Some(detector) = &self.hardware_detector {
+            if let Ok(capabilities) = detector.try_recv() {
+                self.hardware = Some(capabilities);
+                self.hardware_detector = None;
+            }
+        }
+        let mut open = self.show_settings;
+        egui::Window::new("Settings").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.strong("Video Encoder");
+            ui.label("Used when the clip is re-encoded.");
+            hwaccel::encoder_settings_ui(ui, self.hardware.as_ref(), &mut self.settings.encoder, &mut self.settings.hardware_decode);
+            ui.separator();
+
+            ui.strong("Split Parts");
+            ui.label("File names for the parts of a split. They go next to the output and keep its extension.");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.settings.split_template);
+                if ui.button("Reset").clicked() {
+                    self.settings.split_template = split::DEFAULT_TEMPLATE.to_owned();
+                }
+            });
+            ui.label("{name} is the output's name, {n} the part number and {title} the chapter title.");
+        });
+        self.show_settings = open;
+    }
+
+    fn join_window(&mut self, ctx: &egui::Context) {
+        // The clip being edited follows the scrubber until a different video is opened there.
+        if let Some(index) = self.join_editing {
+            match self.join_clips.get_mut(index) {
+                Some(clip) if self.picked_path.as_deref() == Some(clip.path.as_str()) => {
+                    clip.start = self.start_trim;
+                    clip.end = (!self.trim_to_end).then_some(self.end_trim);
+                }
+                _ => self.join_editing = None,
+            }
+        }
+        let mut open = self.show_join;
+        egui::Window::new("Join").open(&mut open).default_width(480.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Add Videos...").clicked() {
+                    let paths = rfd::FileDialog::new()
+                        .set_title("Add Videos to Join")
+                        .add_filter("Video File", &["mp4", "mov", "mkv"])
+                        .pick_files()
+                        .unwrap_or_default();
+                    for path in paths {
+                        let path = path.display().to_string();
+                        match JoinClip::open(path.clone()) {
+                            Some(clip) => self.join_clips.push(clip),
+                            None => {
+                                self.toast(egui_toast::ToastKind::Error, format!("Couldn't read {path}"));
+                            }
+                        }
+                    }
+                }
+                let current = self.picked_path.clone().zip(self.video_info.clone());
+                if ui
+                    .add_enabled(current.is_some(), egui::Button::new("Add Current Trim"))
+                    .on_hover_text("Adds the video that's open, trimmed the way it's set now")
+                    .clicked()
+                {
+                    if let Some((path, info)) = current {
+                        self.join_clips.push(JoinClip {
+                            path,
+                            info,
+                            start: self.start_trim,
+                            end: (!self.trim_to_end).then_some(self.end_trim),
+                        });
+                    }
+                }
+            });
+            if self.join_clips.is_empty() {
+                ui.label("Add the videos to join, in the order they should play.");
+                return;
+            }
+
+            let mut swap = None;
+            let mut remove = None;
+            let mut edit = None;
+            egui::Grid::new("join_clips").num_columns(4).striped(true).show(ui, |ui| {
+                let count = self.join_clips.len();
+                for (index, clip) in self.join_clips.iter().enumerate() {
+                    ui.label(format!("{}.", index + 1));
+                    ui.add(egui::Label::new(clip.name()).truncate(true)).on_hover_text(&clip.path);
+                    if clip.is_trimmed() {
+                        let end = clip.end.unwrap_or(clip.info.duration);
+                        ui.label(format!("{} - {}", num_to_time(clip.start), num_to_time(end)));
+                    } else {
+                        ui.label(format!("Whole video ({})", num_to_time(clip.length())));
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(index > 0, egui::Button::new("⬆").small()).clicked() {
+                            swap = Some((index, index - 1));
+                        }
+                        if ui.add_enabled(index + 1 < count, egui::Button::new("⬇").small()).clicked() {
+                            swap = Some((index, index + 1));
+                        }
+                        if ui
+                            .selectable_label(self.join_editing == Some(index), "Trim")
+                            .on_hover_text("Opens this video in the scrubber. The trim set there is used for it here.")
+                            .clicked()
+                        {
+                            edit = Some(index);
+                        }
+                        if ui.small_button("🗑").on_hover_text("Remove from the join").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+            if let Some((a, b)) = swap {
+                self.join_clips.swap(a, b);
+                self.join_editing = self.join_editing.map(|i| {
+                    if i == a {
+                        b
+                    } else if i == b {
+                        a
+                    } else {
+                        i
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                self.join_clips.remove(index);
+                self.join_editing = match self.join_editing {
+                    Some(i) if i == index => None,
+                    Some(i) if i > index => Some(i - 1),
+                    editing => editing,
+                };
+            }
+            if let Some(index) = edit {
+                let clip = self.join_clips[index].clone();
+                self.picked_path = Some(clip.path);
+                analyze_picked_video(self, ctx);
+                self.start_trim = clip.start;
+                self.end_trim = clip.end.unwrap_or(clip.info.duration);
+                self.trim_to_end = clip.end.is_none();
+                self.load_start_preview(ctx);
+                self.load_end_preview(ctx);
+                self.join_editing = Some(index);
+            }
+            ui.separator();
+
+            let problems = join::copy_problems(&self.join_clips);
+            if problems.is_empty() {
+                ui.colored_label(Color32::LIGHT_GREEN, "These can be joined without re-encoding.");
+                ui.checkbox(&mut self.join_reencode, "Re-encode Anyway")
+                    .on_hover_text("Try this if the joined video glitches where the clips meet");
+            } else {
+                ui.colored_label(Color32::YELLOW, "These have to be re-encoded to join them:");
+                for problem in &problems {
+                    ui.label(format!("• {problem}"));
+                }
+            }
+            if !problems.is_empty() || self.join_reencode {
+                if let Some(([width, height], frame_rate)) = join::target_format(&self.join_clips) {
+                    ui.label(format!(
+                        "Every clip is fitted to {width}x{height} at {frame_rate:.3} fps, like the first one, with 48 kHz stereo audio."
+                    ));
+                }
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Save As...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .set_title("Set Joined Output")
+                        .add_filter("Video File", &["mp4", "mkv"])
+                        .set_file_name("joined.mp4")
+                        .save_file()
+                    {
+                        self.join_output = Some(path.display().to_string());
+                    }
+                }
+                match &self.join_output {
+                    Some(path) => ui.add(egui::Label::new(format!("({path})")).truncate(true)),
+                    None => ui.label("(no output picked)"),
+                };
+            });
+            let can_join = self.join_clips.len() >= 2 && self.join_output.is_some();
+            if ui
+                .add_enabled(can_join, egui::Button::new("Join"))
+                .on_disabled_hover_text("Add at least two videos and pick where the joined video goes")
+                .clicked()
+            {
+                self.join(problems.is_empty() && !self.join_reencode);
+            }
+        });
+        self.show_join = open;
+    }
+
+    fn frames_window(&mut self, ctx: &egui::Context) {
+        if let Some(copier) = &self.clipboard_copier {
+            if let Ok(result) = copier.try_recv() {
+                let (text, kind) = match result {
+                    Ok(()) => ("Copied the frame to the clipboard.".to_owned(), egui_toast::ToastKind::Success),
+                    Err(reason) => (format!("Couldn't copy the frame: {reason}"), egui_toast::ToastKind::Error),
+                };
+                self.toast(kind, text);
+                self.clipboard_copier = None;
+            }
+        }
+        let mut open = self.show_frames;
+        egui::Window::new("Frames").open(&mut open).resizable(false).show(ctx, |ui| {
+            let (Some(path), Some(info)) = (self.picked_path.clone(), &self.video_info) else {
+                ui.label("Open a video to save frames from it.");
+                return;
+            };
+            let [width, height] = info.display_size();
+            let frame_rate = info.frame_rate;
+
+            ui.strong("Single Frame");
+            ui.horizontal(|ui| {
+                for source in [FrameSource::Playhead, FrameSource::Start, FrameSource::End] {
+                    ui.selectable_value(&mut self.frame_source, source, source.name());
+                }
+            });
+            let time = match self.frame_source {
+                FrameSource::Playhead => self.playhead,
+                FrameSource::Start => self.start_trim,
+                FrameSource::End => self.trim_end(),
+            };
+            ui.label(format!("{} at {width:.0}x{height:.0}", num_to_time(time)));
+            ui.horizontal(|ui| {
+                for format in [StillFormat::Png, StillFormat::Jpeg] {
+                    if ui.button(format!("Save as {}...", format.name())).clicked() {
+                        if let Some(output) = rfd::FileDialog::new()
+                            .set_title("Save Frame")
+                            .add_filter(format.name(), &[format.extension()])
+                            .set_file_name(frames::still_name(&path, time, format))
+                            .save_file()
+                        {
+                            let output = output.display().to_string();
+                            self.queue.push(format!("Frame to {output}"), frames::still_args(&path, time, &output));
+                            self.side_panel_tab = SidePanelTab::Queue;
+                        }
+                    }
+                }
+                if ui
+                    .add_enabled(self.clipboard_copier.is_none(), egui::Button::new("Copy to Clipboard"))
+                    .clicked()
+                {
+                    self.clipboard_copier = Some(frames::copy_to_clipboard(ctx, &path, time));
+                }
+                if self.clipboard_copier.is_some() {
+                    ui.spinner();
+                }
+            });
+            ui.separator();
+
+            ui.strong("Image Sequence");
+            ui.horizontal(|ui| {
+                ui.label("Every");
+                ui.add(egui::DragValue::new(&mut self.frame_step).clamp_range(1..=1000).suffix(" frame(s)"));
+                ui.label("of the trim, as");
+                ui.selectable_value(&mut self.sequence_format, StillFormat::Png, StillFormat::Png.name());
+                ui.selectable_value(&mut self.sequence_format, StillFormat::Jpeg, StillFormat::Jpeg.name());
+            });
+            let count = frames::sequence_count(self.trim_end() - self.start_trim, frame_rate, self.frame_step);
+            ui.label(format!(
+                "About {count} images from {} to {}.",
+                num_to_time(self.start_trim),
+                num_to_time(self.trim_end())
+            ));
+            if ui.button("Export to Folder...").clicked() {
+                if let Some(folder) = rfd::FileDialog::new().set_title("Export Frames To").pick_folder() {
+                    let folder = folder.display().to_string();
+                    let args = frames::sequence_args(
+                        &path,
+                        self.start_trim,
+                        self.trim_end(),
+                        self.frame_step,
+                        &folder,
+                        self.sequence_format,
+                        self.overwrite,
+                    );
+                    self.queue.push(format!("Frames to {folder}"), args);
+                    self.side_panel_tab = SidePanelTab::Queue;
+                }
+            }
+            ui.separator();
+
+            ui.strong("Contact Sheet");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.contact_sheet.columns)
+                        .clamp_range(1..=10)
+                        .suffix(" columns"),
+                );
+                ui.label("x");
+                let max_rows = SheetLayout::max_rows(self.contact_sheet.columns);
+                ui.add(
+                    egui::DragValue::new(&mut self.contact_sheet.rows)
+                        .clamp_range(1..=max_rows)
+                        .suffix(" rows"),
+                );
+                let [sheet_width, sheet_height] = self.contact_sheet.size(info);
+                ui.label(format!("({sheet_width}x{sheet_height})"));
+            });
+            ui.label("Thumbnails are spread evenly across the trim, with their times on them.");
+            if ui.button("Save Contact Sheet...").clicked() {
+                if let Some(output) = rfd::FileDialog::new()
+                    .set_title("Save Contact Sheet")
+                    .add_filter("Image", &["png", "jpg"])
+                    .set_file_name(contactsheet::sheet_name(&path))
+                    .save_file()
+                {
+                    let output = output.display().to_string();
+                    let args = contactsheet::sheet_args(&path, info, self.start_trim, self.trim_end(), self.contact_sheet, &output);
+                    self.queue.push(format!("Contact sheet to {output}"), args);
+                    self.side_panel_tab = SidePanelTab::Queue;
+                }
+            }
+        });
+        self.show_frames = open;
+    }
+
+    fn join(&mut self, copy: bool) {
+        let Some(output) = self.join_output.clone() else {
+            return;
+        };
+        let args = if copy {
+            join::copy_args(&self.join_clips, &output, self.overwrite)
+        } else {
+            join::reencode_args(&self.join_clips, &output, self.overwrite)
+        };
+        let Some(args) = args else {
+            self.toast(egui_toast::ToastKind::Error, "Couldn't set up the join.");
+            return;
+        };
+        self.queue.push(format!("Join to {output}"), args);
+        self.side_panel_tab = SidePanelTab::Queue;
+    }
+
+    fn overlays_window(&mut self, ctx: &egui::Context) {
+        if let Some(loader) = &self.overlay_preview_loader {
+            if let Ok(image) = loader.try_recv() {
+                self.overlay_preview = image.map(|image| ctx.load_texture("overlay_preview", image, Default::default()));
+                self.overlay_preview_loader = None;
+            }
+        }
+        if !self.show_overlays {
+            return;
+        }
+        // The preview is the start frame as it will be exported, made small so it's quick to redo.
+        let mut filters = self.video_filters();
+        filters.push(format!("scale=-2:{OVERLAY_PREVIEW_HEIGHT}"));
+        let source = (self.start_trim, filters);
+        let settled = self.overlay_preview_loader.is_none() && !ctx.input(|i| i.pointer.any_down());
+        if let (Some(path), true) = (&self.picked_path, settled) {
+            if self.overlay_preview_source.as_ref() != Some(&source) {
+                self.overlay_preview_loader = Some(reframe::render_mockup(ctx, path, source.0, source.1.clone()));
+                self.overlay_preview_source = Some(source);
+            }
+        }
+
+        let mut open = self.show_overlays;
+        egui::Window::new("Overlays").open(&mut open).default_width(480.0).show(ctx, |ui| {
+            let slot = egui::Rect::from_min_size(ui.cursor().min, vec2(ui.available_width().max(200.0), OVERLAY_PREVIEW_HEIGHT as f32));
+            let (_, response) = ui.allocate_exact_size(slot.size(), egui::Sense::drag());
+            ui.painter().rect_filled(slot, 0.0, Color32::from_gray(20));
+            match &self.overlay_preview {
+                Some(texture) => {
+                    let [width, height] = texture.size();
+                    let rect = letterbox(slot, width as f32 / height as f32);
+                    egui::Image::new((texture.id(), rect.size())).paint_at(ui, rect);
+                    // Dragging moves the selected overlay. The preview catches up once the mouse is let go.
+                    if let (Some(overlay), Some(pointer)) = (self.overlays.get_mut(self.selected_overlay), response.interact_pointer_pos()) {
+                        let size = overlay.preview_size(ui, rect.size());
+                        overlay.move_to(pointer, rect, size);
+                        ui.painter()
+                            .rect_stroke(overlay.preview_rect(rect, size), 0.0, egui::Stroke::new(2.0, Color32::YELLOW));
+                    }
+                }
+                None => {
+                    let text = if self.picked_path.is_some() {
+                        "No preview yet"
+                    } else {
+                        "Open a video to see a preview"
+                    };
+                    ui.painter()
+                        .text(slot.center(), Align2::CENTER_CENTER, text, egui::FontId::default(), Color32::GRAY);
+                }
+            }
+            if self.overlay_preview_loader.is_some() {
+                ui.put(egui::Rect::from_center_size(slot.center(), vec2(24.0, 24.0)), egui::Spinner::new());
+            }
+            ui.separator();
+            overlay::presets_ui(ui, &mut self.settings.overlay_presets, &mut self.overlays, &mut self.overlay_preset_name);
+            ui.separator();
+            overlay::overlay_list_ui(ui, &mut self.overlays, &mut self.selected_overlay);
+        });
+        self.show_overlays = open;
+    }
+
+    fn subtitles_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_subtitles;
+        let output = self.output_location.clone().unwrap_or_else(|| self.output_name.clone());
+        egui::Window::new("Subtitles")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| match &self.video_info {
+                Some(info) => subtitles::subtitle_settings_ui(ui, &info.subtitle_streams, &mut self.subtitles, &output),
+                None => {
+                    ui.label("Open a video to add subtitles to it.");
+                }
+            });
+        self.show_subtitles = open;
+    }
+
+    fn crop_window(&mut self, ctx: &egui::Context) {
+        if let Some(detector) = &self.crop_detector {
+            // If nothing was detected the crop is just left alone.
+            if let Ok(crop) = detector.try_recv() {
+                self.crop_detector = None;
+                if let Some(crop) = crop {
+                    self.crop = crop;
+                    self.crop_aspect_lock = None;
+                }
+            }
+        }
+        let mut open = self.show_crop_editor;
+        egui::Window::new("Crop").open(&mut open).default_width(640.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Aspect");
+                if ui.selectable_label(self.crop_aspect_lock.is_none(), "Free").clicked() {
+                    self.crop_aspect_lock = None;
+                }
+                for (name, aspect) in crop::ASPECT_PRESETS {
+                    if ui.selectable_label(self.crop_aspect_lock == Some(aspect), name).clicked() {
+                        self.crop_aspect_lock = Some(aspect);
+                        self.crop = Crop::with_aspect(self.display_aspect(), aspect);
+                    }
+                }
+                ui.separator();
+                let can_detect = self.picked_path.is_some() && self.video_info.is_some() && self.crop_detector.is_none();
+                if ui
+                    .add_enabled(can_detect, egui::Button::new("Auto Detect"))
+                    .on_hover_text("Finds black bars around the picture")
+                    .clicked()
+                {
+                    if let (Some(path), Some(info)) = (&self.picked_path, &self.video_info) {
+                        self.crop_detector = Some(crop::detect(ctx, path, self.start_trim, info));
+                    }
+                }
+                if self.crop_detector.is_some() {
+                    ui.spinner();
+                }
+                if ui.button("Reset").clicked() {
+                    self.crop = Crop::FULL;
+                    self.crop_aspect_lock = None;
+                }
+            });
+            let aspect = self.display_aspect();
+            let width = ui.available_width().max(200.0);
+            let size = vec2(width, width / aspect.max(0.1));
+            crop::crop_editor(
+                ui,
+                &mut self.crop,
+                self.preview_image_start_handle.as_ref(),
+                aspect,
+                self.crop_aspect_lock,
+                size,
+            );
+            if let Some(info) = &self.video_info {
+                let [width, height, x, y] = self.crop.pixels(info);
+                ui.label(format!("{width}x{height} at {x}, {y}"));
+            }
+        });
+        self.show_crop_editor = open;
+    }
+
+    fn side_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.side_panel_tab, SidePanelTab::Info, "Info");
+            ui.selectable_value(&mut self.side_panel_tab, SidePanelTab::Chapters, "Chapters");
+            ui.selectable_value(&mut self.side_panel_tab, SidePanelTab::Queue, "Queue");
+            ui.selectable_value(&mut self.side_panel_tab, SidePanelTab::Logs, "Logs");
+        });
+        ui.separator();
+        match self.side_panel_tab {
+            SidePanelTab::Info => {
+                let Some(path) = &self.picked_path else {
+                    ui.label("Open a video to see its details.");
+                    return;
+                };
+                egui::Grid::new("video_info").num_columns(2).striped(true).show(ui, |ui| {
+                    ui.label("File");
+                    ui.add(egui::Label::new(path.as_str()).truncate(true));
+                    ui.end_row();
+                    ui.label("Length");
+                    ui.label(num_to_time(self.video_length as f32));
+                    ui.end_row();
+                    if let Some(info) = &self.video_info {
+                        ui.label("Frame Rate");
+                        ui.label(format!("{:.3} fps", info.frame_rate));
+                        ui.end_row();
+                        ui.label("Resolution");
+                        ui.label(format!("{}x{}", info.width, info.height));
+                        ui.end_row();
+                        let [display_width, display_height] = info.display_size();
+                        ui.label("Display Size");
+                        ui.label(format!("{display_width:.0}x{display_height:.0}"));
+                        ui.end_row();
+                        if info.rotation != 0 {
+                            ui.label("Rotation");
+                            ui.label(format!("{}°", info.rotation));
+                            ui.end_row();
+                        }
+                    }
+                    ui.label("Selection");
+                    ui.label(num_to_time(self.end_trim - self.start_trim));
+                    ui.end_row();
+                    if self.speed.is_changed() {
+                        ui.label("Output Length");
+                        ui.label(num_to_time(self.output_length()));
+                        ui.end_row();
+                    }
+                });
+            }
+            SidePanelTab::Chapters => {
+                if self.picked_path.is_none() {
+                    ui.label("Open a video to add markers.");
+                    return;
+                }
+                let shortcut = ui.ctx().format_shortcut(&shortcuts::ADD_MARKER);
+                ui.horizontal(|ui| {
+                    ui.heading("Markers");
+                    if ui.button("Add at Playhead").on_hover_text(&shortcut).clicked() {
+                        chapters::add_marker(&mut self.markers, self.playhead);
+                    }
+                });
+                if self.markers.is_empty() {
+                    ui.label(format!("No markers yet. Press {shortcut} while scrubbing to add one."));
+                }
+                let mut jump = None;
+                let mut remove = None;
+                egui::Grid::new("markers").num_columns(3).striped(true).show(ui, |ui| {
+                    for (index, marker) in self.markers.iter_mut().enumerate() {
+                        if ui.button(num_to_time(marker.time)).on_hover_text("Jump here").clicked() {
+                            jump = Some(marker.time);
+                        }
+                        ui.add(egui::TextEdit::singleline(&mut marker.name).desired_width(120.0));
+                        if ui.small_button("🗑").on_hover_text("Delete this marker").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(time) = jump {
+                    self.set_playhead(time);
+                }
+                if let Some(index) = remove {
+                    self.markers.remove(index);
+                }
+
+                let Some(info) = &self.video_info else {
+                    return;
+                };
+                ui.separator();
+                ui.heading("Chapters");
+                if info.chapters.is_empty() {
+                    ui.label("This video has no chapters.");
+                }
+                let mut picked = None;
+                for (index, chapter) in info.chapters.iter().enumerate() {
+                    let title = chapter.title.clone().unwrap_or_else(|| format!("Chapter {}", index + 1));
+                    let text = format!("{} {title}", num_to_time(chapter.start));
+                    if ui.selectable_label(false, text).on_hover_text("Trim to this chapter").clicked() {
+                        picked = Some((chapter.start, chapter.end));
+                    }
+                }
+                if let Some((start, end)) = picked {
+                    self.trim_to_range(ui.ctx(), start, end);
+                }
+            }
+            SidePanelTab::Queue => {
+                if self.queue.jobs().is_empty() {
+                    ui.label("Nothing has been exported yet.");
+                    return;
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for job in self.queue.jobs() {
+                        ui.horizontal(|ui| {
+                            match job.status {
+                                JobStatus::Queued => ui.label("Queued"),
+                                JobStatus::Running => ui.spinner(),
+                                JobStatus::Finished { success: true } => ui.colored_label(Color32::LIGHT_GREEN, "Done"),
+                                JobStatus::Finished { success: false } => ui.colored_label(Color32::LIGHT_RED, "Failed"),
+                            };
+                            ui.add(egui::Label::new(&job.label).truncate(true));
+                        });
+                    }
+                });
+                if ui.button("Clear Finished").clicked() {
+                    self.queue.clear_finished();
+                }
+            }
+            SidePanelTab::Logs => {
+                egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                    for job in self.queue.jobs().iter().filter(|j| !j.log.is_empty()) {
+                        egui::CollapsingHeader::new(&job.label).id_source(job.id).show(ui, |ui| {
+                            ui.add(egui::Label::new(egui::RichText::new(format!("ffmpeg {}", job.args.join(" "))).strong()).wrap(true));
+                            ui.add(egui::Label::new(egui::RichText::new(&job.log).monospace()).wrap(true));
+                        });
+                    }
+                });
+            }
+        }
+    }
+
+    fn playback_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_playback;
+        egui::Window::new("Playback").open(&mut open).resizable(false).show(ctx, |ui| {
+            let [width, height] = self.playback_size();
+            let size = vec2(width as f32, height as f32);
+            if let Some(texture) = &self.playback_texture {
+                ui.add(egui::Image::new((texture.id(), size)));
+            } else if let Some(texture) = &self.preview_image_start_handle {
+                ui.add(egui::Image::new((texture.id(), size)));
+            } else {
+                ui.allocate_ui(size, |ui| ui.label("Nothing to play yet."));
+            }
+            ui.horizontal(|ui| {
+                let label = if self.playback.is_some() { "Pause" } else { "Play" };
+                if ui.button(label).on_hover_text(ctx.format_shortcut(&shortcuts::PLAY_PAUSE)).clicked() {
+                    self.toggle_playback(ctx);
+                }
+                if ui.button("Play Selection").clicked() {
+                    self.play_selection(ctx);
+                }
+                ui.checkbox(&mut self.loop_selection, "Loop Selection");
+                ui.label(num_to_time(self.playhead));
+            });
+        });
+        if !open {
+            self.stop_playback();
         }
+        self.show_playback = open;
+    }
+
+    fn advance_shuttle(&mut self, ctx: &egui::Context) {
+        if self.shuttle_speed == 0.0 {
+            return;
+        }
+        let dt = ctx.input(|i| i.stable_dt);
+        self.set_playhead(self.playhead + self.shuttle_speed * dt);
+        if self.playhead <= 0.0 || self.playhead >= self.video_length as f32 {
+            self.shuttle_speed = 0.0;
+        }
+        ctx.request_repaint();
     }
 }
 
 impl eframe::App for QuickTrim {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.save(storage);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.set_visuals(eframe::egui::Visuals::dark());
+
+        self.handle_shortcuts(ctx);
+        self.advance_shuttle(ctx);
+        self.update_playback(ctx);
+        self.update_queue(ctx);
+
+        if let Some(loader) = &self.waveform_loader {
+            if let Ok(peaks) = loader.try_recv() {
+                self.waveform = peaks;
+                self.waveform_loader = None;
+            }
+        }
+        if let Some(detector) = &self.scene_detector {
+            if let Ok(cuts) = detector.try_recv() {
+                if cuts.is_none() {
+                    self.toast(egui_toast::ToastKind::Error, "Couldn't look for scene cuts in this video.");
+                }
+                self.scene_cuts = cuts;
+                self.scene_detector = None;
+            }
+        }
+        if let Some(detector) = &self.dead_air_detector {
+            if let Ok(regions) = detector.try_recv() {
+                if regions.is_none() {
+                    self.toast(egui_toast::ToastKind::Error, "Couldn't look for dead air in this video.");
+                }
+                self.dead_air = regions;
+                self.dead_air_detector = None;
+            }
+        }
+        if let Some(loader) = &self.keyframe_loader {
+            if let Ok(keyframes) = loader.try_recv() {
+                if keyframes.is_none() {
+                    self.toast(
+                        egui_toast::ToastKind::Warning,
+                        "Couldn't read the keyframes, so split parts won't line up with them.",
+                    );
+                }
+                // An empty list stops this being retried every frame.
+                self.keyframes = Some(keyframes.unwrap_or_default());
+                self.keyframe_loader = None;
+            }
+        }
+        // Only copies need the keyframes, so they're not read until a split would use them.
+        if let (Some(path), SplitMode::Parts | SplitMode::Length) = (&self.picked_path, self.split.mode) {
+            if self.keyframes.is_none() && self.keyframe_loader.is_none() && self.is_stream_copy() {
+                self.keyframe_loader = Some(split::load_keyframes(ctx, path));
+            }
+        }
+        if let Some(loader) = &self.filmstrip_loader {
+            if let Ok(image) = loader.try_recv() {
+                self.filmstrip = image.map(|image| Filmstrip {
+                    texture: ctx.load_texture("filmstrip", image, Default::default()),
+                    duration: self.video_length as f32,
+                });
+                self.filmstrip_loader = None;
+            }
+        }
+
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("Edit", |ui| {
+                    let undo = egui::Button::new("Undo").shortcut_text(ctx.format_shortcut(&shortcuts::UNDO));
+                    if ui.add_enabled(self.history.can_undo(), undo).clicked() {
+                        self.undo(ctx);
+                        ui.close_menu();
+                    }
+                    let redo = egui::Button::new("Redo").shortcut_text(ctx.format_shortcut(&shortcuts::REDO));
+                    if ui.add_enabled(self.history.can_redo(), redo).clicked() {
+                        self.redo(ctx);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Settings...").clicked() {
+                        self.show_settings = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    if ui.checkbox(&mut self.show_playback, "Playback").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_side_panel, "Side Panel").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_crop_editor, "Crop").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_reframe, "Reframe").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_audio, "Audio").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_subtitles, "Subtitles").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_overlays, "Overlays").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_join, "Join").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_frames, "Frames").clicked() {
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Help", |ui| {
+                    let cheat_sheet = egui::Button::new("Keyboard Shortcuts").shortcut_text(ctx.format_shortcut(&shortcuts::CHEAT_SHEET));
+                    if ui.add(cheat_sheet).clicked() {
+                        self.show_cheat_sheet = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+
+        shortcuts::cheat_sheet_window(ctx, &mut self.show_cheat_sheet);
+        self.playback_window(ctx);
+        self.crop_window(ctx);
+        self.reframe_window(ctx);
+        self.audio_window(ctx);
+        self.subtitles_window(ctx);
+        self.overlays_window(ctx);
+        self.settings_window(ctx);
+        self.join_window(ctx);
+        self.frames_window(ctx);
+
+        egui::TopBottomPanel::bottom("actions").show(ctx, |ui| {
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                if ui.button("Trim").on_hover_text(ctx.format_shortcut(&shortcuts::TRIM)).clicked() {
+                    self.trim();
+                }
+                if ui.button("Refresh Data").clicked() {
+                    self.reset();
+                }
+                if self.pending_trim.is_some() {
+                    ui.spinner();
+                    ui.label("Trimming...");
+                }
+            });
+            ui.add_space(4.0);
+        });
+
+        if self.show_side_panel {
+            egui::SidePanel::right("side_panel")
+                .default_width(260.0)
+                .resizable(true)
+                .show(ctx, |ui| self.side_panel(ui));
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.visuals_mut().override_text_color = Some(Color32::WHITE);
             ui.visuals_mut().panel_fill = Color32::from_hex("#353535").unwrap();
@@ -127,14 +1865,7 @@ impl eframe::App for QuickTrim {
                         ui.label("File");
                         ui.horizontal(|ui| {
                             if ui.button("Open file...").clicked() {
-                                if let Some(path) = rfd::FileDialog::new()
-                                    .set_title("Open File to Trim")
-                                    .add_filter("Video File", &["mp4", "mov", "mkv"])
-                                    .pick_file()
-                                {
-                                    self.picked_path = Some(path.display().to_string());
-                                    analyze_picked_video(self, ui);   
-                                }
+                                self.open_file_dialog(ui.ctx());
                             }
                             if let Some(picked_path) = &self.picked_path {
                                 ui.add(egui::Label::new(format!("({picked_path})")).truncate(true));
@@ -165,15 +1896,10 @@ impl eframe::App for QuickTrim {
                             egui::DragValue::new(&mut self.start_trim)
                                 .clamp_range(0.0..=self.video_length as f32)
                                 .custom_formatter(|n, _| num_to_time(n as f32))
-                                .custom_parser(|s| time_to_num(s)),
+                                .custom_parser(time_to_num),
                         );
                         if trim_start_drag.drag_stopped() || trim_start_drag.lost_focus() {
-                            if let Some(p) = &self.picked_path {
-                                let image_data = get_video_frame(&p, &num_to_time(self.start_trim), &self.preview_image_dimensions);
-                                if let Some(d) = image_data {
-                                    self.preview_image_start_handle = Some(ui.ctx().load_texture("preview_end", d, Default::default()));
-                                }
-                            }
+                            self.load_start_preview(ui.ctx());
                         }
                         ui.end_row();
 
@@ -184,117 +1910,372 @@ impl eframe::App for QuickTrim {
                                 egui::DragValue::new(&mut self.end_trim)
                                     .clamp_range(0.0..=self.video_length as f32)
                                     .custom_formatter(|n, _| num_to_time(n as f32))
-                                    .custom_parser(|s| time_to_num(s)),
+                                    .custom_parser(time_to_num),
                             );
                             if trim_end_drag.drag_stopped() || trim_end_drag.lost_focus() {
-                                if let Some(p) = &self.picked_path {
-                                    let image_data = get_video_frame(&p, &num_to_time(self.end_trim), &self.preview_image_dimensions);
-                                    if let Some(d) = image_data {
-                                        self.preview_image_end_handle = Some(ui.ctx().load_texture("preview_end", d, Default::default()));
+                                self.load_end_preview(ui.ctx());
+                            }
+                            ui.checkbox(&mut self.trim_to_end, "To End")
+                        });
+                        ui.end_row();
+
+                        ui.label("Playhead");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.playhead)
+                                    .clamp_range(0.0..=self.video_length as f32)
+                                    .custom_formatter(|n, _| num_to_time(n as f32))
+                                    .custom_parser(time_to_num),
+                            );
+                            if ui.small_button("Set Start (I)").clicked() {
+                                self.start_trim = self.playhead.min(self.end_trim);
+                                self.load_start_preview(ui.ctx());
+                            }
+                            if ui.small_button("Set End (O)").clicked() {
+                                self.end_trim = self.playhead.max(self.start_trim);
+                                self.trim_to_end = false;
+                                self.load_end_preview(ui.ctx());
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Scene Cuts");
+                        ui.horizontal(|ui| {
+                            let can_detect = self.picked_path.is_some() && self.scene_detector.is_none();
+                            if ui
+                                .add_enabled(can_detect, egui::Button::new("Detect"))
+                                .on_hover_text("Looks through the whole video for cuts and marks them on the timeline")
+                                .clicked()
+                            {
+                                if let Some(path) = &self.picked_path {
+                                    self.scene_detector = Some(scenes::detect(ui.ctx(), path, self.scene_sensitivity));
+                                }
+                            }
+                            ui.add(egui::Slider::new(&mut self.scene_sensitivity, 0.1..=0.9).show_value(false).text("Sensitivity"))
+                                .on_hover_text("Higher finds more cuts, including smaller changes within a shot");
+                            if self.scene_detector.is_some() {
+                                ui.spinner();
+                            } else if let Some(cuts) = self.scene_cuts.clone() {
+                                ui.label(format!("{} found", cuts.len()));
+                                ui.checkbox(&mut self.snap_to_scenes, "Snap").on_hover_text("Snap the trim handles to cuts while dragging them");
+                                if ui.add_enabled(!cuts.is_empty(), egui::Button::new("Start to Nearest")).clicked() {
+                                    if let Some(cut) = scenes::nearest(&cuts, self.start_trim) {
+                                        self.start_trim = cut.min(self.end_trim);
+                                        self.load_start_preview(ui.ctx());
+                                    }
+                                }
+                                if ui.add_enabled(!cuts.is_empty() && !self.trim_to_end, egui::Button::new("End to Nearest")).clicked() {
+                                    if let Some(cut) = scenes::nearest(&cuts, self.end_trim) {
+                                        self.end_trim = cut.max(self.start_trim);
+                                        self.load_end_preview(ui.ctx());
                                     }
                                 }
                             }
-                            ui.checkbox(&mut self.trim_to_end, "To End")
                         });
                         ui.end_row();
 
-                        ui.label("Extra");
+                        ui.label("Dead Air");
                         ui.horizontal(|ui| {
-                            // maybe just check if file exists at output path and if so, add this automatically?
-                            ui.checkbox(&mut self.overwrite, "Overwrite Existing");
-                            ui.checkbox(&mut self.slow_trim, "Slow Trim (Blocking)").on_hover_text("Sometimes trimming using the fast setting (default) can cause the video to have weird artifacts. If the video has a lot of artifacts/glitches/blobs, try turning this setting on. This is a slow process and will freeze the window until complete.");
+                            let can_detect = self.picked_path.is_some() && self.video_info.is_some() && self.dead_air_detector.is_none();
+                            if ui
+                                .add_enabled(can_detect, egui::Button::new("Detect"))
+                                .on_hover_text("Looks through the whole video for silence, and black or frozen picture if ticked")
+                                .clicked()
+                            {
+                                if let (Some(path), Some(info)) = (&self.picked_path, &self.video_info) {
+                                    let has_audio = !info.audio_streams.is_empty();
+                                    self.dead_air_detector = Some(deadair::detect(ui.ctx(), path, info.duration, has_audio, self.dead_air_options));
+                                }
+                            }
+                            ui.checkbox(&mut self.dead_air_options.black, "Black");
+                            ui.checkbox(&mut self.dead_air_options.freeze, "Frozen");
+                            if self.dead_air_detector.is_some() {
+                                ui.spinner();
+                            } else if let Some(regions) = &self.dead_air {
+                                ui.label(format!("({})", deadair::summary(regions)));
+                                if ui.button("Auto-Trim Ends").on_hover_text("Trims off the dead air at the start and end").clicked() {
+                                    let duration = self.video_info.as_ref().map_or(self.video_length as f32, |info| info.duration);
+                                    let (start, end) = deadair::content_range(regions, duration);
+                                    self.start_trim = start;
+                                    self.end_trim = end;
+                                    self.trim_to_end = end >= duration;
+                                    self.load_start_preview(ui.ctx());
+                                    self.load_end_preview(ui.ctx());
+                                }
+                                ui.checkbox(&mut self.remove_silences, "Cut Silences Over");
+                                ui.add_enabled(
+                                    self.remove_silences,
+                                    egui::DragValue::new(&mut self.min_silence).clamp_range(0.5..=30.0).speed(0.05).suffix("s").max_decimals(1),
+                                );
+                                if let Some(segments) = self.segments() {
+                                    let reencodes = if self.slow_trim { "" } else { ", re-encodes" };
+                                    ui.label(format!("({} parts{reencodes})", segments.len()));
+                                }
+                            }
                         });
                         ui.end_row();
-                    });
-            });
 
-            ui.add_space(10.0);
+                        ui.label("Chapters");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("chapter_source")
+                                .selected_text(self.chapter_source.name())
+                                .show_ui(ui, |ui| {
+                                    for source in ChapterSource::ALL {
+                                        ui.selectable_value(&mut self.chapter_source, source, source.name());
+                                    }
+                                });
+                            let count = self.export_chapters().len();
+                            if self.chapter_source != ChapterSource::None {
+                                ui.label(format!("({count} chapters)"));
+                            }
+                            if ui
+                                .add_enabled(count > 0, egui::Button::new("Split by Chapters"))
+                                .on_hover_text("Trims every chapter to its own file next to the output, with the settings above")
+                                .on_disabled_hover_text("There are no chapters to split by")
+                                .clicked()
+                            {
+                                self.split_by_chapters();
+                            }
+                        });
+                        ui.end_row();
 
-            ui.add_visible(
-                self.scrubber_is_visible,
-                scrubber(
-                    &mut self.start_trim,
-                    &mut self.end_trim,
-                    self.video_length,
-                    self.trim_to_end,
-                    self.picked_path.clone(),
-                    &mut self.preview_has_loaded,
-                    &mut self.preview_image_start_handle,
-                    &mut self.preview_image_end_handle,
-                    &self.preview_image_dimensions,
-                ),
-            );
+                        ui.label("Split");
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut self.split.mode, SplitMode::Off, "Off");
+                            ui.selectable_value(&mut self.split.mode, SplitMode::Parts, "Into");
+                            ui.add_enabled(
+                                self.split.mode == SplitMode::Parts,
+                                egui::DragValue::new(&mut self.split.parts).clamp_range(2..=100).suffix(" parts"),
+                            );
+                            ui.selectable_value(&mut self.split.mode, SplitMode::Length, "Every");
+                            ui.add_enabled(
+                                self.split.mode == SplitMode::Length,
+                                egui::DragValue::new(&mut self.split.length)
+                                    .clamp_range(1.0..=86400.0)
+                                    .custom_formatter(|n, _| num_to_time(n as f32))
+                                    .custom_parser(time_to_num),
+                            );
+                            if self.split.mode == SplitMode::Off {
+                                return;
+                            }
+                            if self.keyframe_loader.is_some() {
+                                ui.spinner();
+                                ui.label("Finding keyframes...");
+                                return;
+                            }
+                            let parts = self.split_ranges();
+                            if self.is_stream_copy() {
+                                ui.label(format!("({} parts, cut on keyframes)", parts.len()));
+                            } else {
+                                ui.label(format!("({} parts)", parts.len()));
+                            }
+                            if ui
+                                .button("Split")
+                                .on_hover_text("Trims every part to its own file next to the output, with the settings above")
+                                .clicked()
+                            {
+                                self.queue_parts(parts.into_iter().map(|(start, end)| (start, end, None)).collect());
+                            }
+                        });
+                        ui.end_row();
 
-            
-            let mut toasts = Toasts::new()
-                .anchor(Align2::RIGHT_BOTTOM, (-10.0, -10.0))
-                .direction(egui::Direction::BottomUp);
-            
-            ui.horizontal(|ui| {
-                let buttons_rect = egui::Rect::from_min_size(pos2(8.0, 400.0), vec2(140.0, 45.0));
-                ui.allocate_ui_at_rect(buttons_rect, |ui| {
-                    let mut args;
-                    if ui.button("Trim").clicked() {
-                        ctx.set_cursor_icon(egui::CursorIcon::Progress);
-                        if self.picked_path.is_none() {
-                            toasts.add(egui_toast::Toast {
-                                text: "You need to provide the path to the video you want to trim!".into(),
-                                kind: egui_toast::ToastKind::Error,
-                                options: egui_toast::ToastOptions::default().duration_in_seconds(4.0).show_progress(true),
-                            });
-                        }
-                        if self.output_location.is_none() {
-                            toasts.add(egui_toast::Toast {
-                                text: "You need to provide the path to the output file!".into(),
-                                kind: egui_toast::ToastKind::Error,
-                                options: egui_toast::ToastOptions::default().duration_in_seconds(4.0).show_progress(true),
+                        ui.label("Crop / Resize");
+                        ui.horizontal(|ui| {
+                            if ui.button("Edit Crop...").clicked() {
+                                self.show_crop_editor = true;
+                            }
+                            let reframe_text = if self.reframe.is_on() { "Vertical (9:16)..." } else { "Reframe..." };
+                            if ui.button(reframe_text).clicked() {
+                                self.show_reframe = true;
+                            }
+                            let height_text = |height: Option<u32>| height.map_or("Original Size".to_owned(), |h| format!("{h}p"));
+                            egui::ComboBox::from_id_source("output_height").selected_text(height_text(self.output_height)).show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.output_height, None, height_text(None));
+                                for height in OUTPUT_HEIGHTS {
+                                    ui.selectable_value(&mut self.output_height, Some(height), height_text(Some(height)));
+                                }
                             });
-                        }
-                        // Having these as separate "if" statements lets multiple toasts appear.
-                        if self.picked_path.is_some() && self.output_location.is_some() {
-                            self.trim_can_continue = true;
-                        }
-        
-                        if self.trim_can_continue {
-                            let path = self.picked_path.as_ref().unwrap();
-                            let time_start = &num_to_time(self.start_trim as f32);
-                            let time_end = &num_to_time(self.end_trim as f32);
-                            let output = self.output_location.as_ref().unwrap();
-                            if !self.slow_trim {
-                                args = vec!["-ss", time_start, "-to", time_end, "-i", path, "-c", "copy", output];
-                            } else {
-                                args = vec!["-i", path, "-ss", time_start, "-t", time_end, "-async", "1", output];
+                            let resized = !self.crop.is_full() || self.output_height.is_some() || self.reframe.is_on();
+                            if !self.slow_trim && resized {
+                                ui.label("(re-encodes)").on_hover_text("Cropping, resizing and reframing can't be done with the fast trim, so the clip is re-encoded.");
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Audio");
+                        ui.horizontal(|ui| {
+                            if ui.button("Audio Tracks...").clicked() {
+                                self.show_audio = true;
+                            }
+                            if self.keeps_all_streams() {
+                                ui.label("All tracks (Keep Everything)");
+                            } else if self.video_info.is_some() {
+                                ui.label(self.audio.summary());
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Overlays");
+                        ui.horizontal(|ui| {
+                            if ui.button("Overlays...").clicked() {
+                                self.show_overlays = true;
+                            }
+                            match self.overlays.len() {
+                                0 => ui.label("None"),
+                                1 => ui.label(self.overlays[0].name()),
+                                count => ui.label(format!("{count} overlays")),
+                            };
+                            if !self.slow_trim && !self.overlays.is_empty() {
+                                ui.label("(re-encodes)");
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Subtitles");
+                        ui.horizontal(|ui| {
+                            if ui.button("Subtitles...").clicked() {
+                                self.show_subtitles = true;
                             }
-                            if self.overwrite {
-                                args.push("-y");
+                            if self.keeps_all_streams() {
+                                ui.label("As in the original (Keep Everything)");
+                            } else if let Some(info) = &self.video_info {
+                                ui.label(self.subtitles.summary(&info.subtitle_streams));
                             }
-                            if self.trim_to_end {
+                            if !self.slow_trim && self.burns_in_subtitles() {
+                                ui.label("(re-encodes)");
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Speed");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("speed").selected_text(format!("{}x", self.speed.factor)).show_ui(ui, |ui| {
+                                for factor in Speed::PRESETS {
+                                    ui.selectable_value(&mut self.speed.factor, factor, format!("{factor}x"));
+                                }
+                            });
+                            ui.checkbox(&mut self.speed.reverse, "Reverse")
+                                .on_hover_text("Plays the clip backwards. The whole clip is held in memory while exporting, so keep it short.");
+                            if self.speed.is_changed() {
+                                ui.label(format!("Output: {}", num_to_time(self.output_length())));
                                 if !self.slow_trim {
-                                    args.remove(2);
-                                    args.remove(2);
-                                } else {
-                                    args.remove(4);
-                                    args.remove(4);
+                                    ui.label("(re-encodes)");
                                 }
                             }
-                            let cmd = Command::new("ffmpeg").args(args).output().expect("Error when trimming video!");
-                            if !self.ffmpeg_gen_output_made {
-                                self.ffmpeg_gen_output_made = true;
-                                self.ffmpeg_gen_output = Some(String::from_utf8_lossy(&cmd.stderr).into_owned());
+                        });
+                        ui.end_row();
+
+                        ui.label("Fades");
+                        ui.horizontal(|ui| {
+                            fn seconds(value: &mut f32) -> egui::DragValue<'_> {
+                                egui::DragValue::new(value).clamp_range(0.0..=10.0).speed(0.05).suffix("s").max_decimals(2)
                             }
-        
-                            if cmd.status.success() {
-                                self.trim_finished = true;
+                            ui.label("Video In");
+                            ui.add(seconds(&mut self.fades.video_in)).on_hover_text("Fade from black at the start");
+                            ui.label("Out");
+                            ui.add(seconds(&mut self.fades.video_out)).on_hover_text("Fade to black at the end");
+                            ui.separator();
+                            ui.label("Audio In");
+                            ui.add(seconds(&mut self.fades.audio_in));
+                            ui.label("Out");
+                            ui.add(seconds(&mut self.fades.audio_out));
+                            if !self.slow_trim && self.fades.any() {
+                                ui.label("(re-encodes)");
                             }
-                        }
-                    }
-                    if ui.button("Refresh Data").clicked() {
-                        *self = Self::default();
-                    }
-                });
-            }); 
+                        });
+                        ui.end_row();
+
+                        ui.label("Streams");
+                        ui.horizontal(|ui| {
+                            ui.add_enabled(self.is_stream_copy(), egui::Checkbox::new(&mut self.keep_all_streams, "Keep Everything"))
+                                .on_hover_text("Keeps every stream, chapter and tag of the original. Only works for fast trims with no crop, resize, fades, audio changes or burned in subtitles.")
+                                .on_disabled_hover_text("Only works for fast trims with no crop, resize, fades, audio changes or burned in subtitles.");
+                            if let (true, Some(info)) = (self.keeps_all_streams(), &self.video_info) {
+                                let output = self.output_location.as_deref().unwrap_or(&self.output_name);
+                                let dropped = container::unsupported_streams(&info.streams, output);
+                                if dropped.is_empty() {
+                                    ui.label(format!("({} streams, {} chapters)", info.streams.len(), info.chapters.len()));
+                                } else {
+                                    let reasons: Vec<&str> = dropped.iter().map(|(_, reason)| reason.as_str()).collect();
+                                    ui.colored_label(Color32::YELLOW, format!("({} streams will be left out)", dropped.len()))
+                                        .on_hover_text(reasons.join("\n"));
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Extra");
+                        ui.horizontal(|ui| {
+                            // maybe just check if file exists at output path and if so, add this automatically?
+                            ui.checkbox(&mut self.overwrite, "Overwrite Existing");
+                            ui.checkbox(&mut self.slow_trim, "Slow Trim (Re-encode)").on_hover_text("Sometimes trimming using the fast setting (default) can cause the video to have weird artifacts. If the video has a lot of artifacts/glitches/blobs, try turning this setting on. This re-encodes the whole clip, so it takes a lot longer.");
+                        });
+                        ui.end_row();
+                    });
+            });
+
+            ui.add_space(10.0);
+
+            if self.scrubber_is_visible {
+                // Whatever height the ruler, timeline and waveform don't need goes to the previews.
+                let timeline_height = 16.0 + 40.0 + 20.0 + 40.0 + 5.0 * ui.spacing().item_spacing.y + 5.0;
+                self.preview_pair(ui, ui.available_height() - timeline_height);
+                ui.add_space(5.0);
+
+                let mut shading = self.dead_air.as_deref().map(deadair::shading).unwrap_or_default();
+                // Every other part of a split is tinted, so the boundaries can be checked before running it.
+                let part_colors = [Color32::from_rgb(80, 200, 120), Color32::from_rgb(80, 150, 255)];
+                for (index, (start, end)) in self.split_ranges().into_iter().enumerate() {
+                    shading.push((start..=end, part_colors[index % 2]));
+                }
+                let mut timeline = TrimTimeline::new(&mut self.start_trim, &mut self.end_trim, self.video_length as f32, &mut self.timeline_view)
+                    .playhead(&mut self.playhead)
+                    .lock_end(self.trim_to_end)
+                    .markers(self.scene_cuts.as_deref().unwrap_or_default())
+                    .snap_to_markers(self.snap_to_scenes)
+                    .regions(&shading);
+                if let Some(filmstrip) = &self.filmstrip {
+                    timeline = timeline.background(|painter, rect, visible| filmstrip.paint(painter, rect, visible));
+                }
+                let output = timeline.show(ui);
+                if output.start_released {
+                    self.load_start_preview(ui.ctx());
+                }
+                if output.end_released {
+                    self.load_end_preview(ui.ctx());
+                }
+
+                let source_chapters = self.video_info.as_ref().map(|info| info.chapters.clone()).unwrap_or_default();
+                let empty_text = format!("No chapters. Press {} to add a marker.", ctx.format_shortcut(&shortcuts::ADD_MARKER));
+                let lane = chapters::chapter_lane(
+                    ui,
+                    &source_chapters,
+                    &self.markers,
+                    self.timeline_view.range(),
+                    output.track_rect.width(),
+                    &empty_text,
+                );
+                if let Some(chapter) = lane.clicked_chapter.map(|i| &source_chapters[i]) {
+                    self.trim_to_range(ctx, chapter.start, chapter.end);
+                }
+                if let Some(marker) = lane.clicked_marker {
+                    self.set_playhead(self.markers[marker].time);
+                }
+
+                let lane = waveform::waveform_lane(
+                    ui,
+                    self.waveform.as_ref(),
+                    self.waveform_loader.is_some(),
+                    self.timeline_view.range(),
+                    output.track_rect.width(),
+                );
+                let x = self.timeline_view.time_to_x(lane.rect, self.playhead);
+                if lane.rect.x_range().contains(x) {
+                    ui.painter().vline(x, lane.rect.y_range(), egui::Stroke::new(2.0, Color32::from_hex("#ff5050").unwrap()));
+                }
+            }
 
-            toasts.show(ctx);
+            self.toasts.show(ctx);
 
             if self.trim_finished {
                 egui::Window::new("Output")
@@ -318,7 +2299,7 @@ impl eframe::App for QuickTrim {
                                     self.trim_finished = false;
                                 }
                                 else {
-                                    *self = Self::default();
+                                    self.reset();
                                 }
                             }
                         });
@@ -328,7 +2309,7 @@ impl eframe::App for QuickTrim {
             ctx.input(|i| {
                 if !i.raw.dropped_files.is_empty() && i.raw.dropped_files.len() == 1 {
                     if self.picked_path.is_some() {
-                        *self = Self::default();
+                        self.reset();
                     }
                     let mut files = vec![];
                     files.clone_from(&i.raw.dropped_files);
@@ -343,10 +2324,15 @@ impl eframe::App for QuickTrim {
             });
 
             if self.dropped_file {
-                analyze_picked_video(self, ui);
+                analyze_picked_video(self, ui.ctx());
                 self.dropped_file = false;
             }
         });
+
+        // Only record once a drag has been let go of, so a whole drag is a single undo step.
+        if !ctx.input(|i| i.pointer.any_down()) {
+            self.history.observe(self.trim_state());
+        }
     }
 }
 
@@ -376,206 +2362,6 @@ fn time_to_num(s: &str) -> Option<f64> {
     }
 }
 
-// custom scrubber widget
-pub fn scroll_scrubber(
-    ui: &mut egui::Ui,
-    start: &mut f32,
-    end: &mut f32,
-    video_length: u32,
-    to_end: bool,
-    source_path: Option<String>,
-    preview_loaded: &mut bool,
-    preview_image_start: &mut Option<egui::TextureHandle>,
-    preview_image_end: &mut Option<egui::TextureHandle>,
-    dim: &Vec<i32>,
-) -> egui::Response {
-    let preview_size;
-    if !dim.is_empty() {
-        preview_size = egui::vec2(dim[0] as f32, dim[1] as f32)
-    } else {
-        preview_size = egui::vec2(213.0, 120.0);
-    }
-    let (preview_rect, _) = ui.allocate_exact_size(preview_size, egui::Sense::focusable_noninteractive());
-    let mut start_was_updated = false;
-    let mut end_was_updated = false;
-
-    ui.add_space(5.0);
-
-    let scrub_size = egui::vec2(640.0, 25.0);
-    let drag_size = egui::vec2(640.0, 15.0);
-
-    let trim_step = video_length as f32 / 660.0;
-
-    let (rect, response) = ui.allocate_exact_size(scrub_size, egui::Sense::focusable_noninteractive());
-    let (left_drag_rect, mut left_response) = ui.allocate_exact_size(drag_size, egui::Sense::drag());
-    let (right_drag_rect, mut right_response) = ui.allocate_exact_size(drag_size, egui::Sense::drag());
-
-    let preview_rect_start = egui::Rect::from_center_size(
-        egui::pos2(rect.center().x - (preview_size.x / 2.0) - 5.0, preview_rect.center().y),
-        preview_size,
-    );
-    let preview_rect_end = egui::Rect::from_center_size(
-        egui::pos2(rect.center().x + (preview_size.x / 2.0) + 5.0, preview_rect.center().y),
-        preview_size,
-    );
-    ui.put(preview_rect_start, egui::Label::new("Unable to Load Frame Preview"));
-    ui.put(preview_rect_end, egui::Label::new("Unable to Load Frame Preview"));
-
-    let handle_size = egui::vec2(10.0, 15.0);
-    let half_width = handle_size.x / 2.0;
-    let mut left_drag_scrub_rect = egui::Rect::from_center_size(egui::pos2(rect.left() + half_width, left_drag_rect.center().y), handle_size);
-    let mut right_drag_scrub_rect = egui::Rect::from_center_size(egui::pos2(rect.right() - half_width, right_drag_rect.center().y), handle_size);
-
-    left_response = left_response.on_hover_and_drag_cursor(egui::CursorIcon::ResizeHorizontal);
-    if left_response.dragged() {
-        *preview_loaded = false;
-        if left_response.drag_delta().x > 0.0 {
-            *start += trim_step * left_response.drag_delta().x;
-        }
-        if left_response.drag_delta().x < 0.0 {
-            *start -= f32::abs(trim_step * left_response.drag_delta().x);
-        }
-        left_response.mark_changed();
-    }
-    if left_response.drag_stopped() {
-        start_was_updated = true;
-    }
-
-    right_response = right_response.on_hover_and_drag_cursor(egui::CursorIcon::ResizeHorizontal);
-    if right_response.dragged() && !to_end {
-        *preview_loaded = false;
-        if right_response.drag_delta().x > 0.0 {
-            *end += trim_step * right_response.drag_delta().x;
-        }
-        if right_response.drag_delta().x < 0.0 {
-            *end -= f32::abs(trim_step * right_response.drag_delta().x);
-        }
-        right_response.mark_changed();
-    }
-    if right_response.drag_stopped() {
-        end_was_updated = true;
-    }
-
-    if *start < 0.0 {
-        *start = 0.0;
-    }
-    if *end < 0.0 {
-        *end = 0.0;
-    }
-    if *end > video_length as f32 || (*end != video_length as f32 && to_end) {
-        *end = video_length as f32;
-    }
-
-    if *start > *end {
-        *start = *end;
-    }
-    if *end < *start {
-        *end = *start;
-    }
-
-    let mut scrub_rect = rect;
-
-    let move_start = *start as f32 / trim_step as f32;
-    let mut move_end = *end as f32 / trim_step as f32;
-    scrub_rect.set_left(move_start);
-    scrub_rect.set_right(move_end);
-
-    if scrub_rect.left() < rect.left() {
-        scrub_rect.set_left(rect.left());
-    }
-    if scrub_rect.right() > rect.right() {
-        scrub_rect.set_right(rect.right());
-    }
-
-    if right_drag_scrub_rect.right() > rect.right() {
-        move_end -= rect.right() - right_drag_scrub_rect.right();
-    }
-
-    left_drag_scrub_rect.set_center(egui::pos2(move_start + half_width, left_drag_scrub_rect.center().y));
-    right_drag_scrub_rect.set_center(egui::pos2(move_end - half_width, right_drag_scrub_rect.center().y));
-
-    if left_drag_scrub_rect.left() < left_drag_rect.left() {
-        left_drag_scrub_rect.set_center(egui::pos2(
-            left_drag_scrub_rect.center().x + left_drag_rect.left(),
-            left_drag_scrub_rect.center().y,
-        ));
-    }
-    if left_drag_scrub_rect.right() > left_drag_rect.right() {
-        left_drag_scrub_rect.set_center(egui::pos2(left_drag_rect.right() - half_width, left_drag_scrub_rect.center().y));
-    }
-    if right_drag_scrub_rect.right() > right_drag_rect.right() {
-        right_drag_scrub_rect.set_center(egui::pos2(
-            right_drag_rect.right() - (right_drag_scrub_rect.width() / 2.0),
-            right_drag_scrub_rect.center().y,
-        ));
-    }
-    if right_drag_scrub_rect.left() < right_drag_rect.left() {
-        right_drag_scrub_rect.set_center(egui::pos2(left_drag_rect.left() + half_width, right_drag_scrub_rect.center().y));
-    }
-
-    if ui.is_rect_visible(rect) {
-        if (start_was_updated || end_was_updated) && !*preview_loaded {
-            if let Some(path) = source_path {
-                let image_data = get_video_frame(&path, &num_to_time(if start_was_updated { *start } else { *end }), dim);
-                if start_was_updated {
-                    if let Some(d) = image_data {
-                        *preview_image_start = Some(ui.ctx().load_texture("preview_start", d, Default::default()));
-                    }
-                } else {
-                    if let Some(d) = image_data {
-                        *preview_image_end = Some(ui.ctx().load_texture("preview_start", d, Default::default()));
-                    }
-                }
-                *preview_loaded = true;
-            }
-        }
-        if let Some(data) = preview_image_start {
-            egui::Image::new((data.id(), data.size_vec2())).paint_at(ui, preview_rect_start);
-        }
-        if let Some(data) = preview_image_end {
-            egui::Image::new((data.id(), data.size_vec2())).paint_at(ui, preview_rect_end);
-        }
-        ui.painter()
-            .rect(rect, 0.0, Color32::DARK_GRAY, egui::Stroke::new(1.0, Color32::DARK_GRAY));
-        ui.painter().rect_filled(scrub_rect, 0.0, Color32::LIGHT_YELLOW);
-        ui.painter()
-            .rect_stroke(left_drag_rect, 0.0, egui::Stroke::new(1.0, Color32::from_hex("#7b7b7b").unwrap()));
-        ui.painter()
-            .rect_stroke(right_drag_rect, 0.0, egui::Stroke::new(1.0, Color32::from_hex("#7b7b7b").unwrap()));
-        ui.painter().rect_filled(left_drag_scrub_rect, 0.0, Color32::WHITE);
-        ui.painter().rect_filled(right_drag_scrub_rect, 0.0, Color32::WHITE);
-    }
-
-    response
-}
-
-pub fn scrubber<'a>(
-    start: &'a mut f32,
-    end: &'a mut f32,
-    video_length: u32,
-    to_end: bool,
-    source_path: Option<String>,
-    preview_loaded: &'a mut bool,
-    preview_image_start: &'a mut Option<egui::TextureHandle>,
-    preview_image_end: &'a mut Option<egui::TextureHandle>,
-    dim: &'a Vec<i32>,
-) -> impl egui::Widget + 'a {
-    move |ui: &mut egui::Ui| {
-        scroll_scrubber(
-            ui,
-            start,
-            end,
-            video_length,
-            to_end,
-            source_path,
-            preview_loaded,
-            preview_image_start,
-            preview_image_end,
-            dim,
-        )
-    }
-}
-
 // From https://docs.rs/egui/0.27.2/egui/struct.ColorImage.html#method.from_rgba_unmultiplied
 fn load_image_from_memory(image_data: &[u8]) -> Result<ColorImage, image::ImageError> {
     let image = image::load_from_memory(image_data)?;
@@ -585,7 +2371,36 @@ fn load_image_from_memory(image_data: &[u8]) -> Result<ColorImage, image::ImageE
     Ok(ColorImage::from_rgba_unmultiplied(size, pixels.as_slice()))
 }
 
-fn get_video_frame(path: &str, time: &str, dim: &Vec<i32>) -> Option<ColorImage> {
+// Fits an image with the given aspect ratio inside `slot`, centered, leaving black bars on the sides that are too long.
+fn letterbox(slot: egui::Rect, aspect: f32) -> egui::Rect {
+    let size = if slot.width() / slot.height() > aspect {
+        vec2(slot.height() * aspect, slot.height())
+    } else {
+        vec2(slot.width(), slot.width() / aspect)
+    };
+    egui::Rect::from_center_size(slot.center(), size)
+}
+
+// Every ffmpeg and ffprobe run goes through this, so on Windows they don't flash up a console window.
+fn hidden_command(program: &str) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+// ffmpeg applies the rotation metadata while decoding, so asking for the display size is enough
+// to get frames that are the right way up with square pixels.
+fn get_video_frame(path: &str, time: &str, size: [u32; 2]) -> Option<ColorImage> {
+    get_filtered_frame(path, time, &format!("scale={}:{},setsar=1", size[0], size[1]))
+}
+
+// A single frame run through the `-vf` chain `filter`.
+fn get_filtered_frame(path: &str, time: &str, filter: &str) -> Option<ColorImage> {
     let t = String::from(time);
     let p = String::from(path);
     let args = [
@@ -593,8 +2408,8 @@ fn get_video_frame(path: &str, time: &str, dim: &Vec<i32>) -> Option<ColorImage>
         &t,
         "-i",
         &p,
-        "-s",
-        &format!("{}x{}", dim[0], dim[1]),
+        "-vf",
+        filter,
         "-vframes",
         "1",
         "-c:v",
@@ -603,76 +2418,54 @@ fn get_video_frame(path: &str, time: &str, dim: &Vec<i32>) -> Option<ColorImage>
         "image2pipe",
         "pipe:1",
     ];
-    let f = Command::new("ffmpeg")
-        .creation_flags(CREATE_NO_WINDOW)
-        .args(args)
-        .output()
-        .expect("Cannot read preview image!");
+    let f = hidden_command("ffmpeg").args(args).output().expect("Cannot read preview image!");
     load_image_from_memory(&f.stdout).ok()
 }
 
-pub fn analyze_picked_video(trim: &mut QuickTrim, ui: &mut egui::Ui) {
-    if let None = trim.picked_path {
-        return
-    }
-    let cmd = Command::new("ffprobe")
-        .creation_flags(CREATE_NO_WINDOW)
-        .args([
-            "-v",
-            "error",
-            "-select_streams",
-            "v:0",
-            "-show_entries",
-            "stream=duration",
-            "-of",
-            "default=noprint_wrappers=1:nokey=1",
-            &trim.picked_path.as_ref().unwrap(),
-        ])
-        .output()
-        .expect("Could not get video length!");
-    trim.end_trim = String::from_utf8_lossy(&cmd.stdout).into_owned().trim_end().parse::<f32>().unwrap();
+pub fn analyze_picked_video(trim: &mut QuickTrim, ctx: &egui::Context) {
+    let Some(path) = trim.picked_path.clone() else {
+        return;
+    };
+    let Some(info) = probe::probe(&path) else {
+        // Not something ffprobe can find a video stream in.
+        trim.video_info = None;
+        trim.scrubber_is_visible = false;
+        return;
+    };
+    trim.end_trim = info.duration;
     trim.start_trim = 0.0;
     trim.video_length = trim.end_trim as u32;
+    trim.audio = AudioSettings::for_streams(info.audio_streams.len());
+    trim.subtitles = SubtitleSettings::default();
+    trim.video_info = Some(info);
+    trim.playhead = 0.0;
+    trim.shuttle_speed = 0.0;
     trim.scrubber_is_visible = true;
-    if trim.preview_image_dimensions.is_empty() {
-        let dimensions = Command::new("ffprobe")
-            .creation_flags(CREATE_NO_WINDOW)
-            .args([
-                "-v",
-                "error",
-                "-select_streams",
-                "v:0",
-                "-show_entries",
-                "stream=height,width",
-                "-of",
-                "csv=s=x:p=0",
-                &trim.picked_path.as_ref().unwrap(),
-            ])
-            .output()
-            .ok();
-        if let Some(dim) = dimensions {
-            let dim_text = String::from_utf8_lossy(&dim.stdout).into_owned().trim_end().to_owned();
-            let dim_split: Vec<f32> = dim_text.split("x").map(|d| d.parse::<f32>().unwrap()).collect();
-            let width = dim_split[0];
-            let height = dim_split[1];
-            if width < height {
-                trim.preview_image_dimensions = vec![81, 145];
-            } else if width > height {
-                trim.preview_image_dimensions = vec![257, 145];
-            } else {
-                trim.preview_image_dimensions = vec![145, 145];
-            }
-        }
-    }
-    let image_data_start = get_video_frame(&trim.picked_path.as_ref().unwrap(), &num_to_time(trim.start_trim), &trim.preview_image_dimensions);
+    trim.timeline_view = TimelineView::full(trim.video_length as f32);
+    trim.crop = Crop::FULL;
+    trim.crop_aspect_lock = None;
+    trim.reframe_mockup = None;
+    trim.reframe_mockup_source = None;
+    trim.waveform = None;
+    trim.waveform_loader = Some(waveform::load(ctx, &path));
+    trim.filmstrip = None;
+    trim.scene_cuts = None;
+    trim.scene_detector = None;
+    trim.dead_air = None;
+    trim.dead_air_detector = None;
+    trim.markers.clear();
+    trim.keyframes = None;
+    trim.keyframe_loader = None;
+    trim.filmstrip_loader = Some(filmstrip::load(ctx, &path, trim.video_length as f32));
+    let image_data_start = get_video_frame(&path, &num_to_time(trim.start_trim), trim.preview_frame_size());
     if let Some(d) = image_data_start {
-        trim.preview_image_start_handle = Some(ui.ctx().load_texture("preview_start", d, Default::default()));
+        trim.preview_image_start_handle = Some(ctx.load_texture("preview_start", d, Default::default()));
     } else {
         trim.preview_image_start_handle = None;
     }
-    let image_data_end = get_video_frame(&trim.picked_path.as_ref().unwrap(), &num_to_time(trim.end_trim), &trim.preview_image_dimensions);
+    let image_data_end = get_video_frame(&path, &num_to_time(trim.end_trim), trim.preview_frame_size());
     if let Some(d) = image_data_end {
-        trim.preview_image_end_handle = Some(ui.ctx().load_texture("preview_start", d, Default::default()));
+        trim.preview_image_end_handle = Some(ctx.load_texture("preview_end", d, Default::default()));
     } else {
         trim.preview_image_end_handle = None;
     }
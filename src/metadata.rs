@@ -0,0 +1,234 @@
+// Chapters for the exported clip, written as an ffmetadata file that ffmpeg reads as a second input.
+//
+// The chapters have to be moved to the clip's own timeline: anything outside the trim is dropped,
+// chapters that cross the start or end are cut off there, and everything is shifted so the trim start is 0.
+
+use std::fs;
+
+use crate::{cache, probe::Chapter};
+
+// Chapters shorter than this after clamping aren't worth keeping.
+const MIN_CHAPTER_SECONDS: f32 = 0.1;
+
+// The chapters of `chapters` that fall inside start..end, on the clip's timeline.
+pub fn clamp_chapters(chapters: &[Chapter], start: f32, end: f32) -> Vec<Chapter> {
+    chapters
+        .iter()
+        .filter_map(|chapter| {
+            let chapter_start = chapter.start.max(start) - start;
+            let chapter_end = chapter.end.min(end) - start;
+            (chapter_end - chapter_start >= MIN_CHAPTER_SECONDS).then(|| Chapter {
+                start: chapter_start,
+                end: chapter_end,
+                title: chapter.title.clone(),
+            })
+        })
+        .collect()
+}
+
+// Where `time` on the clip's timeline ends up once only `segments` (in the source's time) are kept and joined.
+fn time_after_cuts(time: f32, start: f32, segments: &[(f32, f32)]) -> f32 {
+    segments.iter().map(|(from, to)| ((to - start).min(time) - (from - start)).max(0.0)).sum()
+}
+
+// Pulls each chapter back by the time cut out before it. Chapters that were entirely inside a cut are dropped.
+pub fn remove_cuts(chapters: Vec<Chapter>, start: f32, segments: &[(f32, f32)]) -> Vec<Chapter> {
+    chapters
+        .into_iter()
+        .map(|chapter| Chapter {
+            start: time_after_cuts(chapter.start, start, segments),
+            end: time_after_cuts(chapter.end, start, segments),
+            ..chapter
+        })
+        .filter(|chapter| chapter.end - chapter.start >= MIN_CHAPTER_SECONDS)
+        .collect()
+}
+
+// Chapters of a clip `length` seconds long once it's played backwards, back in time order.
+pub fn mirror_chapters(chapters: Vec<Chapter>, length: f32) -> Vec<Chapter> {
+    chapters
+        .into_iter()
+        .rev()
+        .map(|chapter| Chapter {
+            start: (length - chapter.end).max(0.0),
+            end: (length - chapter.start).max(0.0),
+            ..chapter
+        })
+        .collect()
+}
+
+pub fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut text = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        text.push_str("[CHAPTER]\nTIMEBASE=1/1000\n");
+        text.push_str(&format!("START={}\n", (chapter.start * 1000.0).round() as u64));
+        text.push_str(&format!("END={}\n", (chapter.end * 1000.0).round() as u64));
+        if let Some(title) = &chapter.title {
+            text.push_str(&format!("title={}\n", escape(title)));
+        }
+    }
+    text
+}
+
+// Writes the chapters for an export into a temp file of its own and returns its path.
+// Returns None if there are no chapters to write.
+pub fn write_chapters(chapters: &[Chapter]) -> Option<String> {
+    if chapters.is_empty() {
+        return None;
+    }
+    let path = cache::job_file("ffmetadata")?;
+    fs::write(&path, ffmetadata(chapters)).ok()?;
+    Some(path.display().to_string())
+}
+
+// Special characters in ffmetadata values are escaped with a backslash.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chapters::{add_marker, marker_chapters, Marker};
+
+    fn chapter(start: f32, end: f32, title: &str) -> Chapter {
+        Chapter {
+            start,
+            end,
+            title: Some(title.to_owned()),
+        }
+    }
+
+    fn times(chapters: &[Chapter]) -> Vec<(f32, f32)> {
+        chapters.iter().map(|c| (c.start, c.end)).collect()
+    }
+
+    #[test]
+    fn chapters_are_clamped_to_the_trim() {
+        let chapters = vec![chapter(0.0, 60.0, "intro"), chapter(60.0, 300.0, "talk"), chapter(300.0, 360.0, "outro")];
+        let clamped = clamp_chapters(&chapters, 45.0, 320.0);
+        assert_eq!(times(&clamped), [(0.0, 15.0), (15.0, 255.0), (255.0, 275.0)]);
+        // Only the bit of a chapter that's left after the cut is kept, and slivers are dropped.
+        assert_eq!(times(&clamp_chapters(&chapters, 59.9375, 120.0)), [(0.0625, 60.0625)]);
+        assert!(clamp_chapters(&chapters, 400.0, 500.0).is_empty());
+        assert!(clamp_chapters(&chapters, 100.0, 100.0).is_empty());
+        assert!(clamp_chapters(&[], 0.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn ffmetadata_file() {
+        let chapters = vec![
+            chapter(0.0, 15.5, "Intro; part=1"),
+            Chapter {
+                start: 15.5,
+                end: 30.0,
+                title: None,
+            },
+        ];
+        assert_eq!(
+            ffmetadata(&chapters),
+            ";FFMETADATA1
+[CHAPTER]
+TIMEBASE=1/1000
+START=0
+END=15500
+title=Intro\\; part\\=1
+[CHAPTER]
+TIMEBASE=1/1000
+START=15500
+END=30000
+"
+        );
+        assert_eq!(ffmetadata(&[]), ";FFMETADATA1\n");
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("plain title"), "plain title");
+        assert_eq!(escape("a=b;c#d\\e"), "a\\=b\\;c\\#d\\\\e");
+        assert_eq!(escape("two\nlines"), "two\\\nlines");
+        assert_eq!(escape(""), "");
+    }
+
+    #[test]
+    fn cuts_pull_later_chapters_back() {
+        // Trim of 10..40 with 15..20 and 30..35 cut out, chapters already on the clip's timeline.
+        let chapters = vec![chapter(0.0, 10.0, "a"), chapter(10.0, 20.0, "b"), chapter(20.0, 30.0, "c")];
+        let segments = [(10.0, 15.0), (20.0, 30.0), (35.0, 40.0)];
+        let moved = remove_cuts(chapters, 10.0, &segments);
+        assert_eq!(times(&moved), [(0.0, 5.0), (5.0, 15.0), (15.0, 20.0)]);
+    }
+
+    #[test]
+    fn chapters_inside_a_cut_are_dropped() {
+        let chapters = vec![chapter(0.0, 4.0, "a"), chapter(5.0, 8.0, "silent"), chapter(8.0, 12.0, "b")];
+        let moved = remove_cuts(chapters, 0.0, &[(0.0, 5.0), (8.0, 12.0)]);
+        assert_eq!(times(&moved), [(0.0, 4.0), (5.0, 9.0)]);
+        assert_eq!(moved[1].title.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn reversed_chapters_are_mirrored_in_time_order() {
+        let chapters = vec![chapter(0.0, 2.0, "a"), chapter(2.0, 7.0, "b"), chapter(7.0, 10.0, "c")];
+        let mirrored = mirror_chapters(chapters, 10.0);
+        assert_eq!(times(&mirrored), [(0.0, 3.0), (3.0, 8.0), (8.0, 10.0)]);
+        let titles: Vec<_> = mirrored.iter().filter_map(|c| c.title.as_deref()).collect();
+        assert_eq!(titles, ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn markers_are_kept_in_time_order() {
+        let mut markers = vec![];
+        for time in [5.0, 2.0, 8.0, 2.0] {
+            add_marker(&mut markers, time);
+        }
+        let added: Vec<_> = markers.iter().map(|m| (m.time, m.name.as_str())).collect();
+        // A marker at the same time as another goes after it.
+        assert_eq!(added, [(2.0, "Marker 2"), (2.0, "Marker 4"), (5.0, "Marker 1"), (8.0, "Marker 3")]);
+    }
+
+    #[test]
+    fn markers_become_chapters() {
+        let marker = |time, name: &str| Marker { time, name: name.to_owned() };
+        let markers = vec![
+            marker(8.0, "c"),
+            marker(2.0, "a"),
+            marker(5.0, "b"),
+            marker(2.0, "again"),
+            marker(12.0, "late"),
+        ];
+        // The bit before the first marker gets an untitled chapter, the second marker at 2s is dropped and the one
+        // past the end is ignored.
+        assert_eq!(
+            marker_chapters(&markers, 10.0),
+            [
+                Chapter {
+                    title: None,
+                    ..chapter(0.0, 2.0, "")
+                },
+                chapter(2.0, 5.0, "a"),
+                chapter(5.0, 8.0, "b"),
+                chapter(8.0, 10.0, "c"),
+            ]
+        );
+        assert_eq!(
+            marker_chapters(&[marker(0.0, "start"), marker(4.0, " ")], 6.0),
+            [
+                chapter(0.0, 4.0, "start"),
+                Chapter {
+                    title: None,
+                    ..chapter(4.0, 6.0, "")
+                }
+            ]
+        );
+        assert!(marker_chapters(&[marker(10.0, "end")], 10.0).is_empty());
+        assert!(marker_chapters(&[], 10.0).is_empty());
+    }
+}
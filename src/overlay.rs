@@ -0,0 +1,392 @@
+// Text and image overlays (captions, logo watermarks) drawn onto the exported clip.
+//
+// They're placed on the output frame, after crop, resize and reframe, so their size and position are fractions of
+// that frame rather than pixels. Text goes through `drawtext`. Images are read with the `movie` source inside the
+// same `-vf` graph, so they don't need an extra input and fit in with the other video filters.
+
+use std::{env, path::Path};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::subtitles::{escape_filter_path, escape_filter_value};
+
+// Fonts that come with Windows, as (name, file in the Fonts folder).
+pub const FONTS: [(&str, &str); 8] = [
+    ("Arial", "arial.ttf"),
+    ("Arial Bold", "arialbd.ttf"),
+    ("Segoe UI", "segoeui.ttf"),
+    ("Segoe UI Bold", "segoeuib.ttf"),
+    ("Impact", "impact.ttf"),
+    ("Consolas", "consola.ttf"),
+    ("Times New Roman", "times.ttf"),
+    ("Comic Sans MS", "comic.ttf"),
+];
+
+// Corners, edges and centre, with a small margin from the edges.
+const ANCHORS: [[(&str, [f32; 2]); 3]; 3] = [
+    [("↖", [0.03, 0.03]), ("↑", [0.5, 0.03]), ("↗", [0.97, 0.03])],
+    [("←", [0.03, 0.5]), ("•", [0.5, 0.5]), ("→", [0.97, 0.5])],
+    [("↙", [0.03, 0.97]), ("↓", [0.5, 0.97]), ("↘", [0.97, 0.97])],
+];
+
+pub fn font_path(file: &str) -> String {
+    let windows = env::var("WINDIR").unwrap_or_else(|_| "C:\\Windows".to_owned());
+    Path::new(&windows).join("Fonts").join(file).display().to_string()
+}
+
+// drawtext's `fontfile=...:` option, or nothing when the file isn't there (the Windows fonts on another system, or
+// a font that's since been removed) so ffmpeg falls back to its default font instead of failing the export.
+pub fn fontfile_option(font: &str) -> String {
+    if Path::new(font).is_file() {
+        format!("fontfile={}:", escape_filter_path(font))
+    } else {
+        String::new()
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum OverlayKind {
+    Text {
+        text: String,
+        // Path to a .ttf/.otf file.
+        font: String,
+        color: [u8; 3],
+        // Dark box behind the text so it stays readable on busy footage.
+        background: bool,
+    },
+    Image {
+        path: String,
+    },
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Overlay {
+    pub kind: OverlayKind,
+    // Where the overlay sits in the room the frame leaves around it. 0.0 is against the left/top edge, 1.0 the right/bottom.
+    pub position: [f32; 2],
+    // Text height as a fraction of the frame's height, or image width as a fraction of its width.
+    pub size: f32,
+    pub opacity: f32,
+}
+
+impl Overlay {
+    pub fn caption() -> Self {
+        Self {
+            kind: OverlayKind::Text {
+                text: "Caption".to_owned(),
+                font: font_path(FONTS[0].1),
+                color: [255, 255, 255],
+                background: true,
+            },
+            position: [0.5, 0.9],
+            size: 0.06,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn watermark(path: String) -> Self {
+        Self {
+            kind: OverlayKind::Image { path },
+            position: [0.97, 0.03],
+            size: 0.15,
+            opacity: 0.8,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match &self.kind {
+            OverlayKind::Text { text, .. } => format!("Text: {text}"),
+            OverlayKind::Image { path } => format!("Image: {}", file_name(path)),
+        }
+    }
+
+    // Roughly how big the overlay comes out on a preview of the frame `frame` points across. Text is measured in
+    // egui's font rather than the one ffmpeg draws with, so it's close but not exact.
+    pub fn preview_size(&self, ui: &egui::Ui, frame: egui::Vec2) -> egui::Vec2 {
+        match &self.kind {
+            OverlayKind::Text { text, .. } => {
+                let font = egui::FontId::proportional(frame.y * self.size);
+                ui.fonts(|f| f.layout_no_wrap(text.clone(), font, egui::Color32::WHITE).size())
+            }
+            OverlayKind::Image { path } => {
+                let width = frame.x * self.size;
+                // Only the header is read, so this is fine to do every frame while dragging.
+                let aspect = image::image_dimensions(path).map_or(1.0, |(w, h)| h as f32 / w.max(1) as f32);
+                egui::vec2(width, width * aspect)
+            }
+        }
+    }
+
+    // Where an overlay `size` big lands on the preview `frame`, placed the same way the filters place it.
+    pub fn preview_rect(&self, frame: egui::Rect, size: egui::Vec2) -> egui::Rect {
+        let room = (frame.size() - size).max(egui::Vec2::ZERO);
+        egui::Rect::from_min_size(frame.min + room * egui::vec2(self.position[0], self.position[1]), size)
+    }
+
+    // Moves the overlay so its middle is under `point` on the preview `frame`, as far as the edges allow.
+    pub fn move_to(&mut self, point: egui::Pos2, frame: egui::Rect, size: egui::Vec2) {
+        let room = (frame.size() - size).max(egui::vec2(1.0, 1.0));
+        let position = ((point - frame.min - size / 2.0) / room).clamp(egui::Vec2::ZERO, egui::vec2(1.0, 1.0));
+        self.position = [position.x, position.y];
+    }
+
+    // The filter for this overlay, None if there's nothing to draw. `index` keeps the graph labels of
+    // several image overlays apart.
+    pub fn filter(&self, index: usize) -> Option<String> {
+        let [x, y] = self.position;
+        match &self.kind {
+            OverlayKind::Text {
+                text,
+                font,
+                color: [r, g, b],
+                background,
+            } => {
+                if text.trim().is_empty() {
+                    return None;
+                }
+                // expansion=none stops `%` in the text being read as a placeholder.
+                let mut filter = format!(
+                    "drawtext={}text={}:expansion=none:fontsize=h*{}:fontcolor=0x{r:02x}{g:02x}{b:02x}@{}:x=(w-text_w)*{x}:y=(h-text_h)*{y}",
+                    fontfile_option(font),
+                    escape_filter_value(text),
+                    self.size,
+                    self.opacity
+                );
+                if *background {
+                    filter.push_str(&format!(":box=1:boxcolor=black@{}:boxborderw=12", self.opacity * 0.6));
+                }
+                Some(filter)
+            }
+            OverlayKind::Image { path } => {
+                if path.is_empty() {
+                    return None;
+                }
+                // The frame so far is labelled so the image can be scaled against it and laid on top.
+                // The image is a single frame, which overlay keeps showing until the clip ends.
+                Some(format!(
+                    "null[frame{index}];\
+                     movie=filename={}[image{index}];\
+                     [image{index}]format=rgba,colorchannelmixer=aa={}[faded{index}];\
+                     [faded{index}][frame{index}]scale2ref=w=main_w*{}:h=ow/a[scaled{index}][base{index}];\
+                     [base{index}][scaled{index}]overlay=x=(W-w)*{x}:y=(H-h)*{y}",
+                    escape_filter_path(path),
+                    self.opacity,
+                    self.size
+                ))
+            }
+        }
+    }
+}
+
+// A named set of overlays that's kept in the settings, like a channel's logo and caption style.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverlayPreset {
+    pub name: String,
+    pub overlays: Vec<Overlay>,
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or_else(|| path.to_owned(), |n| n.to_string_lossy().into_owned())
+}
+
+fn pick_image() -> Option<String> {
+    rfd::FileDialog::new()
+        .set_title("Open Image")
+        .add_filter("Images", &["png", "jpg", "jpeg", "bmp", "gif"])
+        .pick_file()
+        .map(|path| path.display().to_string())
+}
+
+// The list of overlays with add/remove buttons, and the settings of the `selected` one.
+pub fn overlay_list_ui(ui: &mut egui::Ui, overlays: &mut Vec<Overlay>, selected: &mut usize) {
+    ui.horizontal(|ui| {
+        if ui.button("Add Text").clicked() {
+            overlays.push(Overlay::caption());
+            *selected = overlays.len() - 1;
+        }
+        if ui
+            .button("Add Image...")
+            .on_hover_text("PNGs with transparency work best for logos")
+            .clicked()
+        {
+            if let Some(path) = pick_image() {
+                overlays.push(Overlay::watermark(path));
+                *selected = overlays.len() - 1;
+            }
+        }
+        if ui.add_enabled(*selected < overlays.len(), egui::Button::new("Remove")).clicked() {
+            overlays.remove(*selected);
+            *selected = selected.saturating_sub(1);
+        }
+    });
+    if overlays.is_empty() {
+        ui.label("No overlays yet.");
+        return;
+    }
+    for (index, overlay) in overlays.iter().enumerate() {
+        ui.selectable_value(selected, index, overlay.name());
+    }
+    let Some(overlay) = overlays.get_mut(*selected) else {
+        return;
+    };
+    ui.separator();
+
+    egui::Grid::new("overlay_settings").num_columns(2).show(ui, |ui| {
+        match &mut overlay.kind {
+            OverlayKind::Text {
+                text,
+                font,
+                color,
+                background,
+            } => {
+                ui.label("Text");
+                ui.text_edit_singleline(text);
+                ui.end_row();
+
+                ui.label("Font");
+                ui.horizontal(|ui| {
+                    let font_name = FONTS
+                        .iter()
+                        .find(|(_, file)| font_path(file) == *font)
+                        .map_or_else(|| file_name(font), |(name, _)| name.to_string());
+                    egui::ComboBox::from_id_source("overlay_font").selected_text(font_name).show_ui(ui, |ui| {
+                        for (name, file) in FONTS {
+                            ui.selectable_value(font, font_path(file), name);
+                        }
+                    });
+                    if ui.button("Other...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_title("Open Font")
+                            .add_filter("Fonts", &["ttf", "otf"])
+                            .pick_file()
+                        {
+                            *font = path.display().to_string();
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Colour");
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgb(color);
+                    ui.checkbox(background, "Background Box");
+                });
+                ui.end_row();
+
+                ui.label("Size");
+                ui.add(egui::Slider::new(&mut overlay.size, 0.02..=0.2).custom_formatter(|v, _| format!("{:.0}% of height", v * 100.0)));
+                ui.end_row();
+            }
+            OverlayKind::Image { path } => {
+                ui.label("Image");
+                ui.horizontal(|ui| {
+                    ui.add(egui::Label::new(file_name(path)).truncate(true));
+                    if ui.button("Change...").clicked() {
+                        if let Some(picked) = pick_image() {
+                            *path = picked;
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Size");
+                ui.add(egui::Slider::new(&mut overlay.size, 0.05..=1.0).custom_formatter(|v, _| format!("{:.0}% of width", v * 100.0)));
+                ui.end_row();
+            }
+        }
+
+        ui.label("Opacity");
+        ui.add(egui::Slider::new(&mut overlay.opacity, 0.0..=1.0).custom_formatter(|v, _| format!("{:.0}%", v * 100.0)));
+        ui.end_row();
+
+        ui.label("Position");
+        ui.horizontal(|ui| {
+            egui::Grid::new("overlay_anchors").spacing([2.0, 2.0]).show(ui, |ui| {
+                for row in ANCHORS {
+                    for (arrow, position) in row {
+                        if ui.selectable_label(overlay.position == position, arrow).clicked() {
+                            overlay.position = position;
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.vertical(|ui| {
+                ui.add(egui::Slider::new(&mut overlay.position[0], 0.0..=1.0).show_value(false).text("X"));
+                ui.add(egui::Slider::new(&mut overlay.position[1], 0.0..=1.0).show_value(false).text("Y"));
+            });
+        });
+        ui.end_row();
+    });
+}
+
+// Loading, saving and deleting presets. `name` is what's typed into the name box.
+pub fn presets_ui(ui: &mut egui::Ui, presets: &mut Vec<OverlayPreset>, overlays: &mut Vec<Overlay>, name: &mut String) {
+    ui.horizontal(|ui| {
+        ui.menu_button("Load Preset", |ui| {
+            if presets.is_empty() {
+                ui.label("No presets saved yet.");
+            }
+            let mut remove = None;
+            for (index, preset) in presets.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button(&preset.name).clicked() {
+                        *overlays = preset.overlays.clone();
+                        ui.close_menu();
+                    }
+                    if ui.small_button("🗑").on_hover_text("Delete this preset").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                presets.remove(index);
+            }
+        });
+        ui.add(egui::TextEdit::singleline(name).hint_text("Preset name").desired_width(120.0));
+        let can_save = !name.trim().is_empty() && !overlays.is_empty();
+        if ui
+            .add_enabled(can_save, egui::Button::new("Save Preset"))
+            .on_hover_text("Saving with an existing name replaces it")
+            .clicked()
+        {
+            let preset = OverlayPreset {
+                name: name.trim().to_owned(),
+                overlays: overlays.clone(),
+            };
+            match presets.iter_mut().find(|p| p.name == preset.name) {
+                Some(existing) => *existing = preset,
+                None => presets.push(preset),
+            }
+            name.clear();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fonts_use_ffmpeg_default() {
+        assert_eq!(fontfile_option("/no/such/font.ttf"), "");
+        let mut caption = Overlay::caption();
+        if let OverlayKind::Text { font, .. } = &mut caption.kind {
+            *font = "/no/such/font.ttf".to_owned();
+        }
+        let filter = caption.filter(0).unwrap();
+        assert!(filter.starts_with("drawtext=text=Caption:"), "{filter}");
+    }
+
+    #[test]
+    fn existing_fonts_are_passed_on() {
+        let font = std::env::temp_dir().join("quick_trim_test_font.ttf");
+        std::fs::write(&font, b"").unwrap();
+        let option = fontfile_option(&font.display().to_string());
+        let _ = std::fs::remove_file(&font);
+        assert!(option.starts_with("fontfile=") && option.ends_with(':'), "{option}");
+    }
+}
@@ -0,0 +1,128 @@
+// Video playback for the preview pane.
+//
+// ffmpeg decodes on a background thread and writes raw RGBA frames to a pipe, which is a lot cheaper
+// than encoding every frame as a PNG like `get_video_frame` does. Frames are handed to the UI through a
+// small bounded channel, so the decoder only ever runs a few frames ahead of what is being shown.
+
+use std::{
+    io::Read,
+    process::Stdio,
+    sync::mpsc::{sync_channel, Receiver, TryRecvError},
+    thread,
+    time::Instant,
+};
+
+use eframe::egui::{self, ColorImage};
+
+use crate::hidden_command;
+
+// How many decoded frames can wait for the UI before the decoder blocks.
+const FRAME_BUFFER: usize = 8;
+
+pub struct Frame {
+    pub time: f32,
+    pub image: ColorImage,
+}
+
+pub struct Playback {
+    receiver: Receiver<Frame>,
+    started_at: Instant,
+    start_time: f32,
+    next_frame: Option<Frame>,
+    decoder_finished: bool,
+    // Where playback should stop (or loop), e.g. the end trim when playing the selection.
+    pub stop_at: Option<f32>,
+}
+
+impl Playback {
+    pub fn start(ctx: &egui::Context, path: &str, from: f32, size: [usize; 2], fps: f32) -> Self {
+        let (sender, receiver) = sync_channel(FRAME_BUFFER);
+        let path = path.to_owned();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let args = [
+                "-v".to_owned(),
+                "error".to_owned(),
+                "-ss".to_owned(),
+                from.to_string(),
+                "-i".to_owned(),
+                path,
+                "-an".to_owned(),
+                "-vf".to_owned(),
+                format!("fps={fps},scale={}:{}", size[0], size[1]),
+                "-f".to_owned(),
+                "rawvideo".to_owned(),
+                "-pix_fmt".to_owned(),
+                "rgba".to_owned(),
+                "pipe:1".to_owned(),
+            ];
+            let Ok(mut child) = hidden_command("ffmpeg")
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+            else {
+                return;
+            };
+            let mut stdout = child.stdout.take().unwrap();
+            let mut buffer = vec![0u8; size[0] * size[1] * 4];
+            let mut frame_number = 0;
+            while stdout.read_exact(&mut buffer).is_ok() {
+                let frame = Frame {
+                    time: from + frame_number as f32 / fps,
+                    image: ColorImage::from_rgba_unmultiplied(size, &buffer),
+                };
+                // The receiver is dropped when playback is stopped.
+                if sender.send(frame).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+                frame_number += 1;
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        });
+        Self {
+            receiver,
+            started_at: Instant::now(),
+            start_time: from,
+            next_frame: None,
+            decoder_finished: false,
+            stop_at: None,
+        }
+    }
+
+    // Current playback position, going by the wall clock.
+    pub fn position(&self) -> f32 {
+        self.start_time + self.started_at.elapsed().as_secs_f32()
+    }
+
+    // Returns the newest frame that is due to be shown, if any. Frames that are already late get skipped.
+    pub fn poll(&mut self) -> Option<ColorImage> {
+        let now = self.position();
+        let mut due = None;
+        loop {
+            if self.next_frame.is_none() {
+                match self.receiver.try_recv() {
+                    Ok(frame) => self.next_frame = Some(frame),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.decoder_finished = true;
+                        break;
+                    }
+                }
+            }
+            match &self.next_frame {
+                Some(frame) if frame.time <= now => due = self.next_frame.take().map(|f| f.image),
+                _ => break,
+            }
+        }
+        due
+    }
+
+    pub fn finished(&self) -> bool {
+        let reached_stop = matches!(self.stop_at, Some(stop) if self.position() >= stop);
+        reached_stop || (self.decoder_finished && self.next_frame.is_none())
+    }
+}
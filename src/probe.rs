@@ -0,0 +1,410 @@
+// Everything the app needs to know about a video, read with a single ffprobe call.
+
+use std::collections::HashMap;
+
+use crate::hidden_command;
+
+#[derive(Clone)]
+pub struct VideoInfo {
+    pub duration: f32,
+    // Size of the stored frames, before the sample aspect ratio or rotation are applied.
+    pub width: u32,
+    pub height: u32,
+    pub sample_aspect_ratio: f32,
+    // Clockwise rotation the player applies on display, one of 0, 90, 180 or 270.
+    pub rotation: u32,
+    pub frame_rate: f32,
+    pub video_codec: String,
+    pub pixel_format: String,
+    // e.g. "1/15360"
+    pub time_base: String,
+    pub audio_streams: Vec<AudioStream>,
+    pub subtitle_streams: Vec<SubtitleStream>,
+    // Every stream in the file, including the ones above.
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Clone)]
+pub struct StreamInfo {
+    // The stream's index in the file, as used by `-map 0:N`.
+    pub index: usize,
+    // "video", "audio", "subtitle", "data" or "attachment"
+    pub kind: String,
+    pub codec: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Chapter {
+    pub start: f32,
+    pub end: f32,
+    pub title: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct AudioStream {
+    pub codec: String,
+    pub channels: u32,
+    pub sample_rate: u32,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+impl AudioStream {
+    // e.g. "Track 2: Mic (eng, aac, 2ch)"
+    pub fn label(&self, number: usize) -> String {
+        let mut details = vec![];
+        details.extend(self.language.clone());
+        details.push(self.codec.clone());
+        details.push(format!("{}ch", self.channels));
+        match &self.title {
+            Some(title) => format!("Track {number}: {title} ({})", details.join(", ")),
+            None => format!("Track {number} ({})", details.join(", ")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SubtitleStream {
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+impl SubtitleStream {
+    // e.g. "Subtitle 1: English (eng, subrip)"
+    pub fn label(&self, number: usize) -> String {
+        let mut details = vec![];
+        details.extend(self.language.clone());
+        details.push(self.codec.clone());
+        match &self.title {
+            Some(title) => format!("Subtitle {number}: {title} ({})", details.join(", ")),
+            None => format!("Subtitle {number} ({})", details.join(", ")),
+        }
+    }
+}
+
+impl VideoInfo {
+    // Size the video is actually shown at, with non-square pixels and rotation taken into account.
+    pub fn display_size(&self) -> [f32; 2] {
+        let width = self.width as f32 * self.sample_aspect_ratio;
+        let height = self.height as f32;
+        if self.rotation % 180 == 90 {
+            [height, width]
+        } else {
+            [width, height]
+        }
+    }
+
+    pub fn display_aspect(&self) -> f32 {
+        let [width, height] = self.display_size();
+        if height > 0.0 {
+            width / height
+        } else {
+            16.0 / 9.0
+        }
+    }
+}
+
+// Largest size with the given aspect ratio that fits in max_width x max_height, rounded to even numbers
+// since a lot of ffmpeg's filters and encoders don't like odd sizes.
+pub fn fit_size(aspect: f32, max_width: u32, max_height: u32) -> [u32; 2] {
+    let (mut width, mut height) = (max_width as f32, max_width as f32 / aspect);
+    if height > max_height as f32 {
+        height = max_height as f32;
+        width = height * aspect;
+    }
+    let even = |n: f32| ((n / 2.0).round() as u32 * 2).max(2);
+    [even(width), even(height)]
+}
+
+pub fn probe(path: &str) -> Option<VideoInfo> {
+    let output = hidden_command("ffprobe")
+        .args(["-v", "error", "-show_format", "-show_streams", "-show_chapters", "-of", "flat", path])
+        .output()
+        .ok()?;
+    parse_probe(&String::from_utf8_lossy(&output.stdout))
+}
+
+// Everything above out of ffprobe's output. None if there's no video stream.
+fn parse_probe(text: &str) -> Option<VideoInfo> {
+    let fields = parse_flat(text);
+
+    let streams: Vec<String> = (0..)
+        .map(|i| format!("streams.stream.{i}."))
+        .take_while(|prefix| fields.contains_key(&format!("{prefix}index")))
+        .collect();
+    let of_type = |kind: &'static str| {
+        let fields = &fields;
+        streams
+            .iter()
+            .filter(move |prefix| fields.get(&format!("{prefix}codec_type")).map(String::as_str) == Some(kind))
+    };
+    let video = of_type("video").next()?;
+    let field = |name: &str| fields.get(&format!("{video}{name}")).map(String::as_str);
+
+    // Matroska doesn't store a duration per stream, so fall back to the container's.
+    let duration = field("duration")
+        .and_then(|d| d.parse::<f32>().ok())
+        .or_else(|| fields.get("format.duration").and_then(|d| d.parse::<f32>().ok()))?;
+    let width = field("width")?.parse().ok()?;
+    let height = field("height")?.parse().ok()?;
+    let sample_aspect_ratio = field("sample_aspect_ratio")
+        .and_then(|r| parse_ratio(r, ':'))
+        .filter(|r| *r > 0.0)
+        .unwrap_or(1.0);
+    let frame_rate = field("r_frame_rate")
+        .and_then(|r| parse_ratio(r, '/'))
+        .filter(|r| *r > 0.0)
+        .unwrap_or(30.0);
+
+    // Newer files store rotation as display matrix side data (counter-clockwise degrees),
+    // older ones as a "rotate" tag (clockwise degrees).
+    let rotation = (0..)
+        .map(|i| field(&format!("side_data_list.side_data.{i}.rotation")))
+        .take_while(Option::is_some)
+        .flatten()
+        .find_map(|r| r.parse::<f32>().ok())
+        .map(|r| -r)
+        .or_else(|| field("tags.rotate").and_then(|r| r.parse::<f32>().ok()))
+        .unwrap_or(0.0);
+    let rotation = ((rotation.round() as i32).rem_euclid(360) + 45) / 90 % 4 * 90;
+
+    // In the order ffmpeg numbers them for `0:a:N`.
+    let audio_streams = of_type("audio")
+        .map(|prefix| {
+            let field = |name: &str| fields.get(&format!("{prefix}{name}")).filter(|v| !v.is_empty()).cloned();
+            AudioStream {
+                codec: field("codec_name").unwrap_or_else(|| "unknown".to_owned()),
+                channels: field("channels").and_then(|c| c.parse().ok()).unwrap_or(0),
+                sample_rate: field("sample_rate").and_then(|r| r.parse().ok()).unwrap_or(0),
+                language: field("tags.language").filter(|l| l != "und"),
+                title: field("tags.title"),
+            }
+        })
+        .collect();
+
+    // Numbered for `0:s:N` the same way.
+    let subtitle_streams = of_type("subtitle")
+        .map(|prefix| {
+            let field = |name: &str| fields.get(&format!("{prefix}{name}")).filter(|v| !v.is_empty()).cloned();
+            SubtitleStream {
+                codec: field("codec_name").unwrap_or_else(|| "unknown".to_owned()),
+                language: field("tags.language").filter(|l| l != "und"),
+                title: field("tags.title"),
+            }
+        })
+        .collect();
+
+    let streams = streams
+        .iter()
+        .filter_map(|prefix| {
+            let field = |name: &str| fields.get(&format!("{prefix}{name}")).cloned();
+            Some(StreamInfo {
+                index: field("index")?.parse().ok()?,
+                kind: field("codec_type").unwrap_or_default(),
+                codec: field("codec_name").unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    let chapters = (0..)
+        .map(|i| format!("chapters.chapter.{i}."))
+        .take_while(|prefix| fields.contains_key(&format!("{prefix}start_time")))
+        .filter_map(|prefix| {
+            let field = |name: &str| fields.get(&format!("{prefix}{name}")).filter(|v| !v.is_empty()).cloned();
+            Some(Chapter {
+                start: field("start_time")?.parse().ok()?,
+                end: field("end_time")?.parse().ok()?,
+                title: field("tags.title"),
+            })
+        })
+        .collect();
+
+    Some(VideoInfo {
+        duration,
+        width,
+        height,
+        sample_aspect_ratio,
+        rotation: rotation as u32,
+        frame_rate,
+        video_codec: field("codec_name").unwrap_or_default().to_owned(),
+        pixel_format: field("pix_fmt").unwrap_or_default().to_owned(),
+        time_base: field("time_base").unwrap_or_default().to_owned(),
+        audio_streams,
+        subtitle_streams,
+        streams,
+        chapters,
+    })
+}
+
+// Parses ffprobe's "flat" output, e.g. `streams.stream.0.width=1920` or `format.filename="clip.mp4"`.
+fn parse_flat(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_owned(), value.trim().trim_matches('"').to_owned()))
+        .collect()
+}
+
+// "30000/1001" or "4:3" as a number
+fn parse_ratio(text: &str, separator: char) -> Option<f32> {
+    let (num, den) = text.split_once(separator)?;
+    let (num, den) = (num.parse::<f32>().ok()?, den.parse::<f32>().ok()?);
+    if den == 0.0 {
+        None
+    } else {
+        Some(num / den)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // The samples below as probed, for other modules' tests.
+    pub fn phone() -> VideoInfo {
+        parse_probe(PHONE).unwrap()
+    }
+
+    pub fn dvd() -> VideoInfo {
+        parse_probe(DVD).unwrap()
+    }
+
+    // `ffprobe -v error -show_format -show_streams -show_chapters -of flat` on a portrait phone video, cut down to
+    // the fields that are read. Rotation is in the display matrix.
+    const PHONE: &str = r#"streams.stream.0.index=0
+streams.stream.0.codec_name="hevc"
+streams.stream.0.codec_type="video"
+streams.stream.0.width=1920
+streams.stream.0.height=1080
+streams.stream.0.sample_aspect_ratio="1:1"
+streams.stream.0.pix_fmt="yuv420p10le"
+streams.stream.0.r_frame_rate="30/1"
+streams.stream.0.time_base="1/600"
+streams.stream.0.duration="12.345000"
+streams.stream.0.side_data_list.side_data.0.side_data_type="Display Matrix"
+streams.stream.0.side_data_list.side_data.0.displaymatrix="\n00000000:            0       65536           0\n00000001:       -65536           0           0\n00000002:            0           0  1073741824\n"
+streams.stream.0.side_data_list.side_data.0.rotation=-90
+streams.stream.1.index=1
+streams.stream.1.codec_name="aac"
+streams.stream.1.codec_type="audio"
+streams.stream.1.sample_rate="44100"
+streams.stream.1.channels=2
+streams.stream.1.tags.language="und"
+streams.stream.1.tags.handler_name="Core Media Audio"
+format.filename="IMG_0420.MOV"
+format.nb_streams=2
+format.duration="12.345000"
+"#;
+
+    // An anamorphic DVD rip in MKV: non-square pixels, no per-stream duration, an old-style rotate tag, subtitles
+    // and chapters.
+    const DVD: &str = r#"streams.stream.0.index=0
+streams.stream.0.codec_name="mpeg2video"
+streams.stream.0.codec_type="video"
+streams.stream.0.width=720
+streams.stream.0.height=480
+streams.stream.0.sample_aspect_ratio="32:27"
+streams.stream.0.pix_fmt="yuv420p"
+streams.stream.0.r_frame_rate="30000/1001"
+streams.stream.0.time_base="1/1000"
+streams.stream.0.duration="N/A"
+streams.stream.0.tags.rotate="180"
+streams.stream.1.index=1
+streams.stream.1.codec_name="ac3"
+streams.stream.1.codec_type="audio"
+streams.stream.1.sample_rate="48000"
+streams.stream.1.channels=6
+streams.stream.1.tags.language="eng"
+streams.stream.1.tags.title="Surround 5.1"
+streams.stream.2.index=2
+streams.stream.2.codec_name="dvd_subtitle"
+streams.stream.2.codec_type="subtitle"
+streams.stream.2.tags.language="fre"
+chapters.chapter.0.id=0
+chapters.chapter.0.start_time="0.000000"
+chapters.chapter.0.end_time="300.300000"
+chapters.chapter.0.tags.title="Opening"
+chapters.chapter.1.id=1
+chapters.chapter.1.start_time="300.300000"
+chapters.chapter.1.end_time="1500.000000"
+format.filename="movie.mkv"
+format.duration="1500.000000"
+format.tags.title="A = B"
+"#;
+
+    #[test]
+    fn flat_output() {
+        let fields = parse_flat(DVD);
+        assert_eq!(fields["streams.stream.0.codec_name"], "mpeg2video");
+        assert_eq!(fields["streams.stream.0.width"], "720");
+        // Only the first = splits the key from the value.
+        assert_eq!(fields["format.tags.title"], "A = B");
+        assert!(parse_flat("").is_empty());
+    }
+
+    #[test]
+    fn rotated_video() {
+        let info = parse_probe(PHONE).unwrap();
+        assert_eq!((info.width, info.height, info.rotation), (1920, 1080, 90));
+        assert_eq!(info.display_size(), [1080.0, 1920.0]);
+        assert_eq!(info.duration, 12.345);
+        assert_eq!(info.frame_rate, 30.0);
+        assert_eq!(
+            (info.video_codec.as_str(), info.pixel_format.as_str(), info.time_base.as_str()),
+            ("hevc", "yuv420p10le", "1/600")
+        );
+        assert_eq!(info.audio_streams.len(), 1);
+        // "und" isn't worth showing.
+        assert_eq!(info.audio_streams[0].label(1), "Track 1 (aac, 2ch)");
+        assert!(info.subtitle_streams.is_empty() && info.chapters.is_empty());
+    }
+
+    #[test]
+    fn non_square_pixels() {
+        let info = parse_probe(DVD).unwrap();
+        assert_eq!(info.sample_aspect_ratio, 32.0 / 27.0);
+        // Turned upside down, which doesn't swap the sides.
+        assert_eq!(info.rotation, 180);
+        assert_eq!(info.display_size(), [720.0 * 32.0 / 27.0, 480.0]);
+        assert!((info.display_aspect() - 16.0 / 9.0).abs() < 0.001);
+        // Matroska only has the container's duration.
+        assert_eq!(info.duration, 1500.0);
+        assert!((info.frame_rate - 29.97).abs() < 0.001);
+        assert_eq!(info.audio_streams[0].label(1), "Track 1: Surround 5.1 (eng, ac3, 6ch)");
+        assert_eq!(info.subtitle_streams[0].label(1), "Subtitle 1 (fre, dvd_subtitle)");
+        let kinds: Vec<_> = info.streams.iter().map(|s| (s.index, s.kind.as_str())).collect();
+        assert_eq!(kinds, [(0, "video"), (1, "audio"), (2, "subtitle")]);
+        let chapters: Vec<_> = info.chapters.iter().map(|c| (c.start, c.end, c.title.clone())).collect();
+        assert_eq!(chapters, [(0.0, 300.3, Some("Opening".to_owned())), (300.3, 1500.0, None)]);
+    }
+
+    #[test]
+    fn missing_or_broken_fields() {
+        // Audio only, or not a media file at all.
+        assert!(parse_probe("").is_none());
+        assert!(parse_probe("streams.stream.0.index=0\nstreams.stream.0.codec_type=\"audio\"\nformat.duration=\"3.0\"\n").is_none());
+        // A 0:1 aspect ratio means unknown, and a 0/0 frame rate is a guess.
+        let odd = PHONE.replace("\"1:1\"", "\"0:1\"").replace("\"30/1\"", "\"0/0\"");
+        let info = parse_probe(&odd).unwrap();
+        assert_eq!((info.sample_aspect_ratio, info.frame_rate), (1.0, 30.0));
+    }
+
+    #[test]
+    fn rotations_are_rounded_to_quarter_turns() {
+        for (side_data, rotation) in [("90", 270), ("-180", 180), ("-270.0", 270), ("-88.5", 90), ("0", 0)] {
+            let text = PHONE.replace("rotation=-90", &format!("rotation={side_data}"));
+            assert_eq!(parse_probe(&text).unwrap().rotation, rotation, "{side_data}");
+        }
+    }
+
+    #[test]
+    fn sizes_fit_the_box() {
+        assert_eq!(fit_size(16.0 / 9.0, 640, 640), [640, 360]);
+        assert_eq!(fit_size(9.0 / 16.0, 640, 640), [360, 640]);
+        // Rounded to even numbers.
+        assert_eq!(fit_size(1.85, 320, 320), [320, 172]);
+        // Never down to nothing.
+        assert_eq!(fit_size(1000.0, 320, 320), [320, 2]);
+    }
+}
@@ -0,0 +1,142 @@
+// Export queue: ffmpeg jobs run one after another on a worker thread, so trimming no longer freezes the window.
+
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use crate::hidden_command;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished { success: bool },
+}
+
+pub struct Job {
+    pub id: u64,
+    pub label: String,
+    pub args: Vec<String>,
+    pub status: JobStatus,
+    // ffmpeg's stderr, once the job has finished
+    pub log: String,
+    // The first attempt failed and the fallback arguments were run instead.
+    pub fell_back: bool,
+}
+
+enum Event {
+    Started(u64),
+    Finished { id: u64, success: bool, log: String, fell_back: bool },
+}
+
+// (id, args, what to run instead if the args fail)
+type Request = (u64, Vec<String>, Option<Vec<String>>);
+
+// Jobs go to the worker thread, progress comes back from it.
+type Worker = (Sender<Request>, Receiver<Event>);
+
+#[derive(Default)]
+pub struct ExportQueue {
+    jobs: Vec<Job>,
+    next_id: u64,
+    // The worker thread is only started once the first job is added.
+    worker: Option<Worker>,
+}
+
+impl ExportQueue {
+    pub fn push(&mut self, label: impl Into<String>, args: Vec<String>) -> u64 {
+        self.push_with_fallback(label, args, None)
+    }
+
+    // If ffmpeg fails with `args`, `fallback` is run in their place, e.g. the same export in software
+    // when a hardware encoder turns out not to work.
+    pub fn push_with_fallback(&mut self, label: impl Into<String>, args: Vec<String>, fallback: Option<Vec<String>>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let (sender, _) = self.worker.get_or_insert_with(spawn_worker);
+        let _ = sender.send((id, args.clone(), fallback));
+        self.jobs.push(Job {
+            id,
+            label: label.into(),
+            args,
+            status: JobStatus::Queued,
+            log: String::new(),
+            fell_back: false,
+        });
+        id
+    }
+
+    // Picks up progress from the worker. Returns the ids of the jobs that finished since the last call.
+    pub fn poll(&mut self) -> Vec<u64> {
+        let mut finished = vec![];
+        let Some((_, events)) = &self.worker else {
+            return finished;
+        };
+        while let Ok(event) = events.try_recv() {
+            match event {
+                Event::Started(id) => {
+                    if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                        job.status = JobStatus::Running;
+                    }
+                }
+                Event::Finished { id, success, log, fell_back } => {
+                    if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                        job.status = JobStatus::Finished { success };
+                        job.log = log;
+                        job.fell_back = fell_back;
+                    }
+                    finished.push(id);
+                }
+            }
+        }
+        finished
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.id == id)
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.jobs.iter().any(|j| !matches!(j.status, JobStatus::Finished { .. }))
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|j| !matches!(j.status, JobStatus::Finished { .. }));
+    }
+}
+
+fn run_ffmpeg(args: &[String]) -> (bool, String) {
+    match hidden_command("ffmpeg").args(args).output() {
+        Ok(output) => (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned()),
+        Err(e) => (false, format!("Could not run ffmpeg: {e}")),
+    }
+}
+
+fn spawn_worker() -> Worker {
+    let (job_sender, jobs) = channel::<Request>();
+    let (event_sender, events) = channel();
+    thread::spawn(move || {
+        for (id, args, fallback) in jobs {
+            let _ = event_sender.send(Event::Started(id));
+            let (mut success, mut log) = run_ffmpeg(&args);
+            let mut fell_back = false;
+            if let (false, Some(fallback)) = (success, fallback) {
+                // The first log is kept, since it's the one that says why the first attempt failed.
+                let (fallback_success, fallback_log) = run_ffmpeg(&fallback);
+                log = format!("{log}\n--- Failed, retrying with: ffmpeg {} ---\n\n{fallback_log}", fallback.join(" "));
+                success = fallback_success;
+                fell_back = true;
+            }
+            let finished = Event::Finished { id, success, log, fell_back };
+            if event_sender.send(finished).is_err() {
+                break;
+            }
+        }
+    });
+    (job_sender, events)
+}
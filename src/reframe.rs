@@ -0,0 +1,240 @@
+// Vertical (9:16) export for shorts. The frame is either cropped down to a 9:16 window (centred or
+// positioned by the user) or shown whole over a blurred, zoomed-in copy of itself.
+//
+// Everything is done with one `-vf` graph, so it goes through the same export path as crop and resize.
+// The mockup in the Reframe window is the same graph run on a single frame at a small size.
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, ColorImage};
+
+use crate::{crop::Crop, get_filtered_frame, num_to_time};
+
+pub const OUTPUT_SIZE: [u32; 2] = [1080, 1920];
+const ASPECT: f32 = 9.0 / 16.0;
+// The mockup is rendered at a quarter of the output size.
+pub const MOCKUP_SIZE: [u32; 2] = [270, 480];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReframeMode {
+    Off,
+    CenterCrop,
+    Positioned,
+    BlurredBackground,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Reframe {
+    pub mode: ReframeMode,
+    // Where the crop window sits across the frame in Positioned mode, 0.0 is the left edge and 1.0 the right.
+    pub position: f32,
+}
+
+impl Default for Reframe {
+    fn default() -> Self {
+        Self {
+            mode: ReframeMode::Off,
+            position: 0.5,
+        }
+    }
+}
+
+impl Reframe {
+    pub fn is_on(&self) -> bool {
+        self.mode != ReframeMode::Off
+    }
+
+    // The 9:16 output size for an export scaled down to `height`.
+    pub fn output_size(height: Option<u32>) -> [u32; 2] {
+        match height {
+            Some(height) => [((height as f32 * ASPECT / 2.0).round() as u32 * 2).max(2), height],
+            None => OUTPUT_SIZE,
+        }
+    }
+
+    fn window_position(&self) -> f32 {
+        match self.mode {
+            // Past the ends would put the window off the frame.
+            ReframeMode::Positioned => self.position.clamp(0.0, 1.0),
+            _ => 0.5,
+        }
+    }
+
+    // The part of the frame (as fractions, inside `crop`) the 9:16 window covers, for drawing it on the previews.
+    pub fn window(&self, crop: Crop, frame_aspect: f32) -> Option<Crop> {
+        if !matches!(self.mode, ReframeMode::CenterCrop | ReframeMode::Positioned) {
+            return None;
+        }
+        let region_aspect = frame_aspect * crop.width / crop.height;
+        let width = (ASPECT / region_aspect).min(1.0) * crop.width;
+        let height = (region_aspect / ASPECT).min(1.0) * crop.height;
+        Some(Crop {
+            x: crop.x + (crop.width - width) * self.window_position(),
+            y: crop.y + (crop.height - height) / 2.0,
+            width,
+            height,
+        })
+    }
+
+    // The `-vf` graph that turns whatever comes out of the earlier filters into a `size` frame.
+    pub fn filter(&self, [width, height]: [u32; 2]) -> Option<String> {
+        // Everything below assumes square pixels.
+        let square = "scale=trunc(iw*sar/2)*2:ih,setsar=1";
+        let filter = match self.mode {
+            ReframeMode::Off => return None,
+            // Commas inside expressions have to be escaped so they aren't read as the next filter.
+            ReframeMode::CenterCrop | ReframeMode::Positioned => {
+                let crop_width = format!("trunc(min(iw\\,ih*{width}/{height})/2)*2");
+                let crop_height = format!("trunc(min(ih\\,iw*{height}/{width})/2)*2");
+                let position = self.window_position();
+                format!("{square},crop={crop_width}:{crop_height}:(iw-ow)*{position}:(ih-oh)/2,scale={width}:{height}")
+            }
+            ReframeMode::BlurredBackground => {
+                let blur = (height / 96).max(2);
+                format!(
+                    "{square},split[original][copy];\
+                     [copy]scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height},boxblur={blur}:2[background];\
+                     [original]scale={width}:{height}:force_original_aspect_ratio=decrease[foreground];\
+                     [background][foreground]overlay=(W-w)/2:(H-h)/2"
+                )
+            }
+        };
+        Some(filter)
+    }
+}
+
+// Renders the frame at `time` through `filters` on a background thread, for the mockup.
+pub fn render_mockup(ctx: &egui::Context, path: &str, time: f32, filters: Vec<String>) -> Receiver<Option<ColorImage>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let _ = sender.send(get_filtered_frame(&path, &num_to_time(time), &filters.join(",")));
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crop::tests::assert_close;
+
+    const WIDESCREEN: f32 = 16.0 / 9.0;
+    // Width of a 9:16 window across a 16:9 frame.
+    const WINDOW_WIDTH: f32 = 0.31640625;
+
+    fn reframe(mode: ReframeMode, position: f32) -> Reframe {
+        Reframe { mode, position }
+    }
+
+    #[test]
+    fn window_on_a_widescreen_frame() {
+        let window = reframe(ReframeMode::CenterCrop, 0.0).window(Crop::FULL, WIDESCREEN).unwrap();
+        assert_close(
+            window,
+            Crop {
+                x: (1.0 - WINDOW_WIDTH) / 2.0,
+                y: 0.0,
+                width: WINDOW_WIDTH,
+                height: 1.0,
+            },
+        );
+        // A frame narrower than 9:16 keeps its width and loses some height instead.
+        let window = reframe(ReframeMode::CenterCrop, 0.5).window(Crop::FULL, 0.5).unwrap();
+        assert_close(
+            window,
+            Crop {
+                x: 0.0,
+                y: 0.5 / 9.0,
+                width: 1.0,
+                height: 8.0 / 9.0,
+            },
+        );
+        assert!(reframe(ReframeMode::Off, 0.5).window(Crop::FULL, WIDESCREEN).is_none());
+        assert!(reframe(ReframeMode::BlurredBackground, 0.5).window(Crop::FULL, WIDESCREEN).is_none());
+    }
+
+    #[test]
+    fn panning_stops_at_the_edges() {
+        let at = |position| reframe(ReframeMode::Positioned, position).window(Crop::FULL, WIDESCREEN).unwrap();
+        assert_close(
+            at(0.0),
+            Crop {
+                x: 0.0,
+                y: 0.0,
+                width: WINDOW_WIDTH,
+                height: 1.0,
+            },
+        );
+        assert_close(
+            at(1.0),
+            Crop {
+                x: 1.0 - WINDOW_WIDTH,
+                y: 0.0,
+                width: WINDOW_WIDTH,
+                height: 1.0,
+            },
+        );
+        assert_close(at(-0.5), at(0.0));
+        assert_close(at(1.5), at(1.0));
+    }
+
+    #[test]
+    fn window_stays_inside_the_crop() {
+        let crop = Crop {
+            x: 0.25,
+            y: 0.1,
+            width: 0.5,
+            height: 0.8,
+        };
+        for position in [0.0, 0.3, 1.0, 2.0] {
+            let window = reframe(ReframeMode::Positioned, position).window(crop, WIDESCREEN).unwrap();
+            assert!(window.x >= crop.x && window.y >= crop.y, "{window:?}");
+            assert!(window.x + window.width <= crop.x + crop.width + 1e-6, "{window:?}");
+            assert!(window.y + window.height <= crop.y + crop.height + 1e-6, "{window:?}");
+            // Still 9:16 on screen.
+            assert!((window.width * WIDESCREEN / window.height - ASPECT).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn crop_filters() {
+        assert_eq!(
+            reframe(ReframeMode::CenterCrop, 0.2).filter(OUTPUT_SIZE).unwrap(),
+            "scale=trunc(iw*sar/2)*2:ih,setsar=1,crop=trunc(min(iw\\,ih*1080/1920)/2)*2:trunc(min(ih\\,iw*1920/1080)/2)*2:(iw-ow)*0.5:(ih-oh)/2,scale=1080:1920"
+        );
+        assert_eq!(
+            reframe(ReframeMode::Positioned, 2.0).filter([406, 720]).unwrap(),
+            "scale=trunc(iw*sar/2)*2:ih,setsar=1,crop=trunc(min(iw\\,ih*406/720)/2)*2:trunc(min(ih\\,iw*720/406)/2)*2:(iw-ow)*1:(ih-oh)/2,scale=406:720"
+        );
+        assert!(reframe(ReframeMode::Off, 0.5).filter(OUTPUT_SIZE).is_none());
+    }
+
+    #[test]
+    fn blurred_background_filter() {
+        assert_eq!(
+            reframe(ReframeMode::BlurredBackground, 0.5).filter(MOCKUP_SIZE).unwrap(),
+            "scale=trunc(iw*sar/2)*2:ih,setsar=1,split[original][copy];\
+             [copy]scale=270:480:force_original_aspect_ratio=increase,crop=270:480,boxblur=5:2[background];\
+             [original]scale=270:480:force_original_aspect_ratio=decrease[foreground];\
+             [background][foreground]overlay=(W-w)/2:(H-h)/2"
+        );
+        // The blur never goes below 2.
+        assert!(reframe(ReframeMode::BlurredBackground, 0.5)
+            .filter([72, 128])
+            .unwrap()
+            .contains("boxblur=2:2"));
+    }
+
+    #[test]
+    fn output_sizes_are_even() {
+        assert_eq!(Reframe::output_size(None), OUTPUT_SIZE);
+        assert_eq!(Reframe::output_size(Some(1280)), [720, 1280]);
+        assert_eq!(Reframe::output_size(Some(720)), [406, 720]);
+        assert_eq!(Reframe::output_size(Some(2)), [2, 2]);
+    }
+}
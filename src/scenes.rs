@@ -0,0 +1,124 @@
+// Scene change detection, for suggesting cut points on the timeline.
+//
+// ffmpeg scores how different every frame is from the one before (`select`'s `scene` value) and `showinfo` logs the
+// frames that score above the threshold. The video is decoded in full, so this runs on a background thread and the
+// result is cached on disk per threshold.
+
+use std::{
+    fs,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui;
+
+use crate::{cache, hidden_command};
+
+pub const DEFAULT_SENSITIVITY: f32 = 0.7;
+// Frames are scored at this width, which is plenty to tell shots apart and much quicker than full size.
+const ANALYSIS_WIDTH: u32 = 320;
+
+// Sensitivity goes up as the threshold goes down, which reads better on a slider.
+fn threshold(sensitivity: f32) -> f32 {
+    (1.0 - sensitivity).clamp(0.05, 0.95)
+}
+
+// Starts finding the scene cuts in `path`, in seconds. The receiver gets None if ffmpeg couldn't read the video.
+pub fn detect(ctx: &egui::Context, path: &str, sensitivity: f32) -> Receiver<Option<Vec<f32>>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let ctx = ctx.clone();
+    let threshold = threshold(sensitivity);
+    thread::spawn(move || {
+        let cache_path = cache::cache_file(&path, &format!("scenes{:.2}", threshold));
+        let cached = cache_path.as_ref().and_then(|p| fs::read_to_string(p).ok());
+        let cuts = match cached {
+            Some(text) => Some(text.lines().filter_map(|l| l.parse().ok()).collect()),
+            None => {
+                let cuts = find_cuts(&path, threshold);
+                if let (Some(cuts), Some(cache_path)) = (&cuts, &cache_path) {
+                    let text: Vec<String> = cuts.iter().map(f32::to_string).collect();
+                    let _ = fs::write(cache_path, text.join("\n"));
+                }
+                cuts
+            }
+        };
+        let _ = sender.send(cuts);
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+fn find_cuts(path: &str, threshold: f32) -> Option<Vec<f32>> {
+    let filter = format!("scale={ANALYSIS_WIDTH}:-2,select='gt(scene,{threshold})',showinfo");
+    let output = hidden_command("ffmpeg")
+        .args(["-hide_banner", "-i", path, "-an", "-sn", "-vf", &filter, "-f", "null", "-"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_showinfo(&String::from_utf8_lossy(&output.stderr)))
+}
+
+// showinfo logs a line per frame that gets through, with the time as e.g. "pts_time:12.345".
+fn parse_showinfo(log: &str) -> Vec<f32> {
+    log.lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| line.split_once("pts_time:"))
+        .filter_map(|(_, rest)| rest.split_whitespace().next()?.parse().ok())
+        .collect()
+}
+
+// The cut closest to `time`.
+pub fn nearest(cuts: &[f32], time: f32) -> Option<f32> {
+    cuts.iter().copied().min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // stderr of `ffmpeg -hide_banner -i clip.mp4 -an -sn -vf scale=320:-2,select='gt(scene,0.3)',showinfo -f null -`,
+    // with the long checksum columns cut short.
+    const LOG: &str = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'clip.mp4':
+  Duration: 00:00:42.04, start: 0.000000, bitrate: 2481 kb/s
+  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(tv, bt709, progressive), 1920x1080 [SAR 1:1 DAR 16:9], 2349 kb/s, 29.97 fps, 29.97 tbr, 30k tbn (default)
+Stream mapping:
+  Stream #0:0 -> #0:0 (h264 (native) -> wrapped_avframe (native))
+Press [q] to stop, [?] for help
+[Parsed_showinfo_2 @ 0x55d5c8a4b2c0] config in time_base: 1/30000, frame_rate: 30000/1001
+[Parsed_showinfo_2 @ 0x55d5c8a4b2c0] config out time_base: 0/0, frame_rate: 0/0
+[Parsed_showinfo_2 @ 0x55d5c8a4b2c0] n:   0 pts: 300300 pts_time:10.01   duration:   1001 duration_time:0.0333667 fmt:yuv420p cl:left sar:1/1 s:320x180 i:P iskey:0 type:P checksum:4B5C2F1E
+[Parsed_showinfo_2 @ 0x55d5c8a4b2c0]   color_range:tv color_space:bt709 color_primaries:bt709 color_trc:bt709
+[Parsed_showinfo_2 @ 0x55d5c8a4b2c0] n:   1 pts: 754754 pts_time:25.1585 duration:   1001 duration_time:0.0333667 fmt:yuv420p cl:left sar:1/1 s:320x180 i:P iskey:1 type:I checksum:9A01C3D2
+frame=    2 fps=0.0 q=-0.0 Lsize=N/A time=00:00:25.19 bitrate=N/A speed= 112x
+";
+
+    #[test]
+    fn showinfo_times() {
+        assert_eq!(parse_showinfo(LOG), [10.01, 25.1585]);
+    }
+
+    #[test]
+    fn empty_log_has_no_cuts() {
+        assert!(parse_showinfo("").is_empty());
+        // A video with no scene changes only gets the config lines.
+        let config_only: String = LOG.lines().filter(|l| !l.contains("pts_time")).collect::<Vec<_>>().join("\n");
+        assert!(parse_showinfo(&config_only).is_empty());
+    }
+
+    #[test]
+    fn nearest_cut() {
+        assert_eq!(nearest(&[10.0, 25.0, 40.0], 20.0), Some(25.0));
+        assert_eq!(nearest(&[10.0, 25.0, 40.0], 0.0), Some(10.0));
+        assert_eq!(nearest(&[], 5.0), None);
+    }
+
+    #[test]
+    fn sensitivity_is_kept_in_range() {
+        assert_eq!(threshold(DEFAULT_SENSITIVITY), 1.0 - DEFAULT_SENSITIVITY);
+        assert_eq!(threshold(1.0), 0.05);
+        assert_eq!(threshold(0.0), 0.95);
+    }
+}
@@ -0,0 +1,40 @@
+// Preferences that are kept between launches. They're saved through eframe's storage, next to the window size.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{hwaccel::VideoEncoder, overlay::OverlayPreset, split};
+
+const STORAGE_KEY: &str = "settings";
+
+// Missing fields fall back to their defaults, so settings saved by an older version still load.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub overlay_presets: Vec<OverlayPreset>,
+    // Only used if it's detected on this machine, see `hwaccel::Capabilities::resolve`.
+    pub encoder: VideoEncoder,
+    pub hardware_decode: bool,
+    // File names for the parts of a split, see `split::part_path`.
+    pub split_template: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            overlay_presets: vec![],
+            encoder: VideoEncoder::default(),
+            hardware_decode: false,
+            split_template: split::DEFAULT_TEMPLATE.to_owned(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage.and_then(|s| eframe::get_value(s, STORAGE_KEY)).unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+}
@@ -0,0 +1,66 @@
+// Keyboard shortcuts and the cheat-sheet window that lists them.
+
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+
+pub const UNDO: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+pub const REDO: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+pub const OPEN_FILE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::O);
+pub const TRIM: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Enter);
+
+pub const MARK_IN: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::I);
+pub const MARK_OUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::O);
+pub const ADD_MARKER: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::M);
+pub const SECOND_BACK: KeyboardShortcut = KeyboardShortcut::new(Modifiers::SHIFT, Key::ArrowLeft);
+pub const SECOND_FORWARD: KeyboardShortcut = KeyboardShortcut::new(Modifiers::SHIFT, Key::ArrowRight);
+pub const FRAME_BACK: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::ArrowLeft);
+pub const FRAME_FORWARD: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::ArrowRight);
+pub const JUMP_START: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Home);
+pub const JUMP_END: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::End);
+pub const SHUTTLE_REVERSE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::J);
+pub const SHUTTLE_STOP: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::K);
+pub const SHUTTLE_FORWARD: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::L);
+pub const PLAY_PAUSE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Space);
+pub const CHEAT_SHEET: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F1);
+
+// Fastest the J/K/L shuttle will go, in either direction.
+pub const MAX_SHUTTLE_SPEED: f32 = 8.0;
+
+// Shortcuts that are not a single KeyboardShortcut get their text written out by hand.
+fn cheat_sheet_rows(ctx: &egui::Context) -> Vec<(String, &'static str)> {
+    let f = |s: &KeyboardShortcut| ctx.format_shortcut(s);
+    vec![
+        (f(&OPEN_FILE), "Open a video"),
+        (f(&TRIM), "Trim"),
+        (f(&UNDO), "Undo"),
+        (f(&REDO), "Redo"),
+        (f(&MARK_IN), "Set start trim at the playhead"),
+        (f(&MARK_OUT), "Set end trim at the playhead"),
+        (f(&ADD_MARKER), "Add a marker at the playhead"),
+        (format!("{} / {}", f(&FRAME_BACK), f(&FRAME_FORWARD)), "Step one frame"),
+        (format!("{} / {}", f(&SECOND_BACK), f(&SECOND_FORWARD)), "Step one second"),
+        (f(&JUMP_START), "Jump to the start of the video"),
+        (f(&JUMP_END), "Jump to the end of the video"),
+        (f(&PLAY_PAUSE), "Play/pause the preview"),
+        (f(&SHUTTLE_REVERSE), "Shuttle backwards (press again to go faster)"),
+        (f(&SHUTTLE_STOP), "Stop shuttling and playback"),
+        (f(&SHUTTLE_FORWARD), "Shuttle forwards (press again to go faster)"),
+        (f(&CHEAT_SHEET), "Show/hide this list"),
+    ]
+}
+
+pub fn cheat_sheet_window(ctx: &egui::Context, open: &mut bool) {
+    egui::Window::new("Keyboard Shortcuts")
+        .open(open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, (0.0, 0.0))
+        .show(ctx, |ui| {
+            egui::Grid::new("cheat_sheet").num_columns(2).spacing([20.0, 6.0]).striped(true).show(ui, |ui| {
+                for (keys, action) in cheat_sheet_rows(ctx) {
+                    ui.monospace(keys);
+                    ui.label(action);
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,250 @@
+// Splitting the trim into equal parts or fixed-length chunks, each exported as its own trim.
+//
+// A stream copy can only start a part on a keyframe, so for copies the boundaries are moved to the nearest keyframe
+// first. Otherwise every part would start at the keyframe before its boundary and repeat the end of the one before.
+
+use std::{
+    fs,
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui;
+
+use crate::{cache, hidden_command, scenes};
+
+pub const DEFAULT_TEMPLATE: &str = "{name}_{n} {title}";
+// Parts shorter than this get merged into the one before.
+const MIN_PART_SECONDS: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SplitMode {
+    Off,
+    Parts,
+    Length,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct SplitSettings {
+    pub mode: SplitMode,
+    pub parts: u32,
+    // Length of each chunk in seconds. The last one gets whatever is left.
+    pub length: f32,
+}
+
+impl Default for SplitSettings {
+    fn default() -> Self {
+        Self {
+            mode: SplitMode::Off,
+            parts: 2,
+            length: 600.0,
+        }
+    }
+}
+
+impl SplitSettings {
+    // The parts of start..end, in the source's time. `keyframes` is given for stream copies, to snap the boundaries to.
+    pub fn ranges(&self, start: f32, end: f32, keyframes: Option<&[f32]>) -> Vec<(f32, f32)> {
+        if end <= start {
+            return vec![];
+        }
+        let mut cuts = vec![];
+        match self.mode {
+            SplitMode::Off => return vec![],
+            SplitMode::Parts => {
+                let parts = self.parts.max(1);
+                cuts.extend((1..parts).map(|i| start + (end - start) * i as f32 / parts as f32));
+            }
+            SplitMode::Length => {
+                let mut cut = start + self.length.max(MIN_PART_SECONDS);
+                while cut < end - MIN_PART_SECONDS {
+                    cuts.push(cut);
+                    cut += self.length.max(MIN_PART_SECONDS);
+                }
+            }
+        }
+        if let Some(keyframes) = keyframes.filter(|k| !k.is_empty()) {
+            // Times are passed to ffmpeg in hundredths, so the cut is rounded up to stay on or just after the
+            // keyframe. Rounding down would start the copy from the keyframe before.
+            cuts = cuts
+                .iter()
+                .filter_map(|cut| scenes::nearest(keyframes, *cut))
+                .map(|keyframe| (keyframe * 100.0).ceil() / 100.0)
+                .collect();
+        }
+        let mut edges = vec![start];
+        for cut in cuts {
+            if cut - edges.last().unwrap() >= MIN_PART_SECONDS && end - cut >= MIN_PART_SECONDS {
+                edges.push(cut);
+            }
+        }
+        edges.push(end);
+        edges.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+}
+
+// Where part `number` of `count` goes. `{name}` is the output's file name without the extension, `{n}` the part
+// number and `{title}` the chapter's title when splitting by chapters. The output's extension is kept.
+pub fn part_path(output: &str, template: &str, number: usize, count: usize, title: Option<&str>) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    // Titles can have characters Windows doesn't allow in file names.
+    let title: String = title
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') && !c.is_control())
+        .collect();
+    let width = count.to_string().len().max(2);
+    let mut name = template
+        .replace("{name}", &stem)
+        .replace("{n}", &format!("{number:0width$}"))
+        .replace("{title}", title.trim())
+        .trim()
+        .to_owned();
+    // A template without {n} would give every part the same name.
+    if !template.contains("{n}") {
+        name.push_str(&format!("_{number:0width$}"));
+    }
+    if let Some(extension) = path.extension() {
+        name.push_str(&format!(".{}", extension.to_string_lossy()));
+    }
+    path.with_file_name(name).display().to_string()
+}
+
+// Starts reading the times of the video's keyframes. The receiver gets None if ffprobe couldn't read the video.
+pub fn load_keyframes(ctx: &egui::Context, path: &str) -> Receiver<Option<Vec<f32>>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let cache_path = cache::cache_file(&path, "keyframes");
+        let cached = cache_path.as_ref().and_then(|p| fs::read_to_string(p).ok());
+        let keyframes = match cached {
+            Some(text) => Some(text.lines().filter_map(|l| l.parse().ok()).collect()),
+            None => {
+                let keyframes = find_keyframes(&path);
+                if let (Some(keyframes), Some(cache_path)) = (&keyframes, &cache_path) {
+                    let text: Vec<String> = keyframes.iter().map(f32::to_string).collect();
+                    let _ = fs::write(cache_path, text.join("\n"));
+                }
+                keyframes
+            }
+        };
+        let _ = sender.send(keyframes);
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+// Only the packets are read, not decoded, so this is quick even for long videos.
+fn find_keyframes(path: &str) -> Option<Vec<f32>> {
+    let output = hidden_command("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "packet=pts_time,flags",
+            "-of",
+            "csv=p=0",
+            path,
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_packets(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Lines look like "12.345000,K__", with K in the flags for keyframes.
+fn parse_packets(text: &str) -> Vec<f32> {
+    let mut keyframes: Vec<f32> = text
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .filter(|(_, flags)| flags.starts_with('K'))
+        .filter_map(|(time, _)| time.parse().ok())
+        .collect();
+    keyframes.sort_by(f32::total_cmp);
+    keyframes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `ffprobe -v error -select_streams v:0 -show_entries packet=pts_time,flags -of csv=p=0` on a clip with B-frames,
+    // so the packets aren't in presentation order. The N/A line is a packet without a timestamp.
+    const PACKETS: &str = "0.000000,K__
+0.100100,___
+0.033367,___
+0.066733,___
+4.004000,K__
+4.104100,___
+N/A,___
+14.014000,K__
+9.976643,K_
+10.010000,___
+";
+
+    fn split(mode: SplitMode, parts: u32, length: f32) -> SplitSettings {
+        SplitSettings { mode, parts, length }
+    }
+
+    #[test]
+    fn equal_parts() {
+        assert_eq!(
+            split(SplitMode::Parts, 3, 0.0).ranges(0.0, 30.0, None),
+            [(0.0, 10.0), (10.0, 20.0), (20.0, 30.0)]
+        );
+        assert_eq!(split(SplitMode::Parts, 1, 0.0).ranges(5.0, 30.0, None), [(5.0, 30.0)]);
+        assert!(split(SplitMode::Off, 3, 0.0).ranges(0.0, 30.0, None).is_empty());
+    }
+
+    #[test]
+    fn fixed_length_chunks() {
+        let chunks = split(SplitMode::Length, 0, 10.0);
+        assert_eq!(chunks.ranges(0.0, 25.0, None), [(0.0, 10.0), (10.0, 20.0), (20.0, 25.0)]);
+        // A last chunk under a second goes onto the one before.
+        assert_eq!(chunks.ranges(0.0, 20.5, None), [(0.0, 10.0), (10.0, 20.5)]);
+    }
+
+    #[test]
+    fn zero_length_range() {
+        assert!(split(SplitMode::Parts, 2, 0.0).ranges(5.0, 5.0, None).is_empty());
+        assert!(split(SplitMode::Length, 0, 10.0).ranges(5.0, 5.0, Some(&[0.0, 5.0])).is_empty());
+    }
+
+    #[test]
+    fn copies_cut_on_keyframes() {
+        let keyframes = parse_packets(PACKETS);
+        assert_eq!(keyframes, [0.0, 4.004, 9.976643, 14.014]);
+        // 10.0 snaps back to the keyframe at 9.976643, rounded up so the copy doesn't start a GOP early.
+        assert_eq!(
+            split(SplitMode::Parts, 2, 0.0).ranges(0.0, 20.0, Some(&keyframes)),
+            [(0.0, 9.98), (9.98, 20.0)]
+        );
+        // No keyframes read means nothing to snap to.
+        assert_eq!(split(SplitMode::Parts, 2, 0.0).ranges(0.0, 20.0, Some(&[])), [(0.0, 10.0), (10.0, 20.0)]);
+    }
+
+    #[test]
+    fn packet_flags() {
+        assert!(parse_packets("").is_empty());
+        assert!(parse_packets("0.000000,___\n").is_empty());
+    }
+
+    #[test]
+    fn part_names() {
+        assert_eq!(part_path("/videos/clip.mp4", DEFAULT_TEMPLATE, 1, 3, None), "/videos/clip_01.mp4");
+        assert_eq!(
+            part_path("/videos/clip.mp4", DEFAULT_TEMPLATE, 2, 3, Some("Intro: Part 1?")),
+            "/videos/clip_02 Intro Part 1.mp4"
+        );
+        assert_eq!(part_path("/videos/clip.mkv", DEFAULT_TEMPLATE, 7, 120, None), "/videos/clip_007.mkv");
+        // Without {n} the number is added anyway so the parts don't overwrite each other.
+        assert_eq!(part_path("/videos/clip.mp4", "{title}", 1, 2, Some("A")), "/videos/A_01.mp4");
+    }
+}
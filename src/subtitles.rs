@@ -0,0 +1,278 @@
+// Subtitles for the export, from one of the video's own subtitle streams or a separate .srt/.ass file.
+//
+// Soft subtitles are mapped into the output and seeked along with the video, so they stay in sync with the cut.
+// MP4 and WebM can only hold one text format each, so they get converted on the way. Burning in goes through the
+// `subtitles` filter, which reads the file itself from the start and so needs the clip's timestamps moved back
+// to where they were in the source while it runs.
+
+use std::path::Path;
+
+use eframe::egui;
+
+use crate::{container, num_to_time, probe::SubtitleStream};
+
+#[derive(Clone, PartialEq)]
+pub enum SubtitleSource {
+    // `0:s:N`
+    Stream(usize),
+    File(String),
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SubtitlePlan {
+    pub source: SubtitleSource,
+    // Codec of the source subtitles, e.g. "subrip" or "hdmv_pgs_subtitle".
+    pub codec: String,
+    pub burn_in: bool,
+}
+
+impl SubtitlePlan {
+    // Subtitles that are pictures (Blu-ray, DVD) rather than text can't be converted or burned in with the subtitles filter.
+    pub fn is_text(&self) -> bool {
+        matches!(self.codec.as_str(), "subrip" | "ass" | "ssa" | "mov_text" | "webvtt" | "text")
+    }
+
+    // What to store soft subtitles as in a file with this extension, None if they can't go in at all.
+    pub fn soft_codec(&self, extension: &str) -> Option<&'static str> {
+        match extension {
+            ext if container::is_mp4_family(ext) => self.is_text().then_some("mov_text"),
+            "webm" => self.is_text().then_some("webvtt"),
+            _ if !self.is_text() || matches!(self.codec.as_str(), "ass" | "ssa") => Some("copy"),
+            _ => Some("srt"),
+        }
+    }
+
+    // `subtitles` filter for burning these in, for a clip cut from `input` at `start`.
+    pub fn burn_in_filter(&self, input: &str, start: f32) -> String {
+        let subtitles = match &self.source {
+            SubtitleSource::Stream(number) => format!("subtitles=filename={}:si={number}", escape_filter_path(input)),
+            SubtitleSource::File(path) => format!("subtitles=filename={}", escape_filter_path(path)),
+        };
+        format!("setpts=PTS+{start}/TB,{subtitles},setpts=PTS-{start}/TB")
+    }
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct SubtitleSettings {
+    // None exports without subtitles.
+    pub selected: Option<SubtitleSource>,
+    // The last file that was opened, so it stays in the list after picking something else.
+    pub file: Option<String>,
+    pub burn_in: bool,
+    // Also save the trimmed subtitles as an .srt next to the export.
+    pub sidecar: bool,
+}
+
+impl SubtitleSettings {
+    pub fn plan(&self, streams: &[SubtitleStream]) -> Option<SubtitlePlan> {
+        let source = self.selected.clone()?;
+        let codec = match &source {
+            SubtitleSource::Stream(number) => streams.get(*number)?.codec.clone(),
+            SubtitleSource::File(path) => file_codec(path).to_owned(),
+        };
+        let mut plan = SubtitlePlan {
+            source,
+            codec,
+            burn_in: false,
+        };
+        plan.burn_in = self.burn_in && plan.is_text();
+        Some(plan)
+    }
+
+    pub fn burns_in(&self, streams: &[SubtitleStream]) -> bool {
+        self.plan(streams).is_some_and(|p| p.burn_in)
+    }
+
+    pub fn summary(&self, streams: &[SubtitleStream]) -> String {
+        let name = match &self.selected {
+            None => return "None".to_owned(),
+            Some(SubtitleSource::Stream(number)) => streams.get(*number).map_or_else(|| "Missing stream".to_owned(), |s| s.label(number + 1)),
+            Some(SubtitleSource::File(path)) => file_name(path),
+        };
+        if self.burns_in(streams) {
+            format!("{name}, burned in")
+        } else {
+            name
+        }
+    }
+}
+
+fn file_codec(path: &str) -> &'static str {
+    match container::extension(path).as_str() {
+        "ass" | "ssa" => "ass",
+        "vtt" => "webvtt",
+        _ => "subrip",
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or_else(|| path.to_owned(), |n| n.to_string_lossy().into_owned())
+}
+
+// The .srt that goes next to `output`.
+pub fn sidecar_path(output: &str) -> String {
+    Path::new(output).with_extension("srt").display().to_string()
+}
+
+// Arguments for a separate ffmpeg run that writes the subtitles for start..end to an .srt.
+pub fn sidecar_args(input: &str, source: &SubtitleSource, start: f32, end: Option<f32>, output: &str, overwrite: bool) -> Vec<String> {
+    let mut args: Vec<String> = vec![];
+    if overwrite {
+        args.push("-y".into());
+    }
+    args.extend(["-ss".into(), num_to_time(start)]);
+    if let Some(end) = end {
+        args.extend(["-to".into(), num_to_time(end)]);
+    }
+    let (input, map) = match source {
+        SubtitleSource::Stream(number) => (input.to_owned(), format!("0:s:{number}")),
+        SubtitleSource::File(path) => (path.clone(), "0:0".to_owned()),
+    };
+    args.extend(["-i".into(), input, "-map".into(), map, "-c:s".into(), "srt".into(), output.to_owned()]);
+    args
+}
+
+// Backslashes are swapped for forward slashes before escaping, which Windows is fine with.
+pub fn escape_filter_path(path: &str) -> String {
+    escape_filter_value(&path.replace('\\', "/"))
+}
+
+// Filter options are escaped twice, once for the option itself and once for the graph around it.
+pub fn escape_filter_value(value: &str) -> String {
+    let escape = |text: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let option = escape(value, &['\\', ':', '\'']);
+    escape(&option, &['\\', '\'', ',', ';', '[', ']'])
+}
+
+pub fn subtitle_settings_ui(ui: &mut egui::Ui, streams: &[SubtitleStream], settings: &mut SubtitleSettings, output: &str) {
+    ui.radio_value(&mut settings.selected, None, "No subtitles");
+    for (number, stream) in streams.iter().enumerate() {
+        ui.radio_value(&mut settings.selected, Some(SubtitleSource::Stream(number)), stream.label(number + 1));
+    }
+    ui.horizontal(|ui| {
+        if let Some(file) = settings.file.clone() {
+            ui.radio_value(&mut settings.selected, Some(SubtitleSource::File(file.clone())), file_name(&file));
+        }
+        if ui.button("Open .srt/.ass...").clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .set_title("Open Subtitles")
+                .add_filter("Subtitles", &["srt", "ass", "ssa", "vtt"])
+                .pick_file()
+            {
+                let path = path.display().to_string();
+                settings.selected = Some(SubtitleSource::File(path.clone()));
+                settings.file = Some(path);
+            }
+        }
+    });
+    ui.separator();
+
+    let plan = settings.plan(streams);
+    let is_text = plan.as_ref().is_some_and(SubtitlePlan::is_text);
+    ui.add_enabled(is_text, egui::Checkbox::new(&mut settings.burn_in, "Burn In"))
+        .on_hover_text("Draws the subtitles onto the video. This re-encodes the clip.")
+        .on_disabled_hover_text("Only text subtitles can be burned in.");
+    ui.add_enabled(is_text, egui::Checkbox::new(&mut settings.sidecar, "Also Save as .srt"))
+        .on_hover_text(format!("Writes the subtitles for the trimmed part to {}", sidecar_path(output)));
+    if let Some(plan) = plan.filter(|p| !p.burn_in) {
+        match plan.soft_codec(&container::extension(output)) {
+            Some(_) => ui.label("The subtitles are added as a track that can be turned on and off."),
+            None => ui.colored_label(
+                egui::Color32::YELLOW,
+                format!(
+                    "These subtitles can't be stored in .{}. Burn them in or export to .mkv.",
+                    container::extension(output)
+                ),
+            ),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(source: SubtitleSource, codec: &str) -> SubtitlePlan {
+        SubtitlePlan {
+            source,
+            codec: codec.to_owned(),
+            burn_in: true,
+        }
+    }
+
+    #[test]
+    fn windows_paths_are_escaped_for_the_graph() {
+        // Once for the option (\ : ') and again for the graph (\ ' , ; [ ]).
+        assert_eq!(escape_filter_path(r"C:\Users\a'b\subs,[1].srt"), r"C\\:/Users/a\\\'b/subs\,\[1\].srt");
+        assert_eq!(escape_filter_value("00:01:02.50"), r"00\\:01\\:02.50");
+        assert_eq!(escape_filter_value("a;b"), r"a\;b");
+        assert_eq!(escape_filter_value("plain"), "plain");
+    }
+
+    #[test]
+    fn soft_codec_by_container() {
+        let srt = plan(SubtitleSource::Stream(0), "subrip");
+        let ass = plan(SubtitleSource::Stream(0), "ass");
+        let pgs = plan(SubtitleSource::Stream(0), "hdmv_pgs_subtitle");
+        assert_eq!(srt.soft_codec("mp4"), Some("mov_text"));
+        assert_eq!(srt.soft_codec("mov"), Some("mov_text"));
+        assert_eq!(srt.soft_codec("mkv"), Some("srt"));
+        assert_eq!(srt.soft_codec("webm"), Some("webvtt"));
+        // ASS keeps its styling, and pictures can only be copied.
+        assert_eq!(ass.soft_codec("mkv"), Some("copy"));
+        assert_eq!(pgs.soft_codec("mkv"), Some("copy"));
+        assert_eq!(pgs.soft_codec("mp4"), None);
+        assert_eq!(pgs.soft_codec("webm"), None);
+    }
+
+    #[test]
+    fn burn_in_is_offset_by_the_start() {
+        let stream = plan(SubtitleSource::Stream(1), "subrip");
+        assert_eq!(
+            stream.burn_in_filter(r"C:\clip.mkv", 12.5),
+            r"setpts=PTS+12.5/TB,subtitles=filename=C\\:/clip.mkv:si=1,setpts=PTS-12.5/TB"
+        );
+        let file = plan(SubtitleSource::File("/subs/clip.srt".to_owned()), "subrip");
+        assert_eq!(
+            file.burn_in_filter("clip.mp4", 0.0),
+            "setpts=PTS+0/TB,subtitles=filename=/subs/clip.srt,setpts=PTS-0/TB"
+        );
+    }
+
+    #[test]
+    fn sidecar_is_cut_to_the_trim() {
+        assert_eq!(
+            sidecar_args("clip.mkv", &SubtitleSource::Stream(2), 5.0, Some(65.25), "clip.srt", true),
+            [
+                "-y",
+                "-ss",
+                "00:00:05.00",
+                "-to",
+                "00:01:05.25",
+                "-i",
+                "clip.mkv",
+                "-map",
+                "0:s:2",
+                "-c:s",
+                "srt",
+                "clip.srt"
+            ]
+        );
+        assert_eq!(
+            sidecar_args("clip.mkv", &SubtitleSource::File("subs.ass".to_owned()), 5.0, None, "clip.srt", false),
+            ["-ss", "00:00:05.00", "-i", "subs.ass", "-map", "0:0", "-c:s", "srt", "clip.srt"]
+        );
+        assert_eq!(sidecar_path("/videos/clip.mp4"), "/videos/clip.srt");
+    }
+}
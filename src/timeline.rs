@@ -0,0 +1,449 @@
+// The trim timeline: a range slider with both trim handles on one zoomable track, a time ruler above it
+// and an optional playhead. It only needs `egui`, so it can be dropped into other egui apps as well.
+//
+//     let output = TrimTimeline::new(&mut start, &mut end, duration, &mut view)
+//         .playhead(&mut playhead)
+//         .show(ui);
+//     if output.start_released { /* reload whatever depends on the start */ }
+
+use std::ops::RangeInclusive;
+
+use eframe::egui::{self, Color32, WidgetInfo, WidgetType};
+
+// How far in the timeline can be zoomed, as the shortest visible span in seconds.
+const MIN_VISIBLE_SECONDS: f32 = 0.5;
+
+// Tick spacings the ruler can pick from, in seconds.
+const TICK_STEPS: [f32; 17] = [0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1800.0, 3600.0, 7200.0];
+
+// Labels on the ruler are kept at least this far apart.
+const MIN_LABEL_SPACING: f32 = 70.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct TimelineView {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Default for TimelineView {
+    fn default() -> Self {
+        Self { start: 0.0, end: 1.0 }
+    }
+}
+
+impl TimelineView {
+    pub fn full(duration: f32) -> Self {
+        Self {
+            start: 0.0,
+            end: duration.max(MIN_VISIBLE_SECONDS),
+        }
+    }
+
+    pub fn span(&self) -> f32 {
+        self.end - self.start
+    }
+
+    pub fn range(&self) -> RangeInclusive<f32> {
+        self.start..=self.end
+    }
+
+    pub fn seconds_per_pixel(&self, rect: egui::Rect) -> f32 {
+        self.span() / rect.width()
+    }
+
+    pub fn time_to_x(&self, rect: egui::Rect, time: f32) -> f32 {
+        rect.left() + (time - self.start) / self.seconds_per_pixel(rect)
+    }
+
+    pub fn x_to_time(&self, rect: egui::Rect, x: f32) -> f32 {
+        self.start + (x - rect.left()) * self.seconds_per_pixel(rect)
+    }
+
+    // Zooms by `factor` (above 1.0 zooms in) while keeping `anchor` at the same spot on screen.
+    pub fn zoom(&mut self, factor: f32, anchor: f32, duration: f32) {
+        let max_span = duration.max(MIN_VISIBLE_SECONDS);
+        let span = (self.span() / factor).clamp(MIN_VISIBLE_SECONDS.min(max_span), max_span);
+        let anchor_fraction = (anchor - self.start) / self.span();
+        self.start = anchor - anchor_fraction * span;
+        self.end = self.start + span;
+        self.pan(0.0, duration);
+    }
+
+    // Moves the view by `seconds`, without going past either end of the video.
+    pub fn pan(&mut self, seconds: f32, duration: f32) {
+        let span = self.span();
+        self.start = (self.start + seconds).clamp(0.0, (duration - span).max(0.0));
+        self.end = self.start + span;
+    }
+
+    // Scrolls just enough to bring `time` back into view, e.g. to follow the playhead.
+    pub fn follow(&mut self, time: f32, duration: f32) {
+        if time > self.end {
+            self.pan(time - self.end, duration);
+        } else if time < self.start {
+            self.pan(time - self.start, duration);
+        }
+    }
+}
+
+pub fn paint_ruler(painter: &egui::Painter, rect: egui::Rect, view: &TimelineView) {
+    painter.rect_filled(rect, 0.0, Color32::from_hex("#2a2a2a").unwrap());
+    let seconds_per_pixel = view.seconds_per_pixel(rect);
+    let step = TICK_STEPS
+        .iter()
+        .copied()
+        .find(|step| step / seconds_per_pixel >= MIN_LABEL_SPACING)
+        .unwrap_or(*TICK_STEPS.last().unwrap());
+    let minor_step = step / 5.0;
+    let stroke = egui::Stroke::new(1.0, Color32::from_hex("#7b7b7b").unwrap());
+
+    let mut tick = (view.start / minor_step).floor() as i64;
+    loop {
+        let time = tick as f32 * minor_step;
+        if time > view.end {
+            break;
+        }
+        let x = view.time_to_x(rect, time);
+        if tick % 5 == 0 {
+            painter.vline(x, rect.y_range(), stroke);
+            painter.text(
+                egui::pos2(x + 3.0, rect.top()),
+                egui::Align2::LEFT_TOP,
+                ruler_label(time, step),
+                egui::FontId::proportional(10.0),
+                Color32::LIGHT_GRAY,
+            );
+        } else {
+            painter.vline(x, (rect.bottom() - 4.0)..=rect.bottom(), stroke);
+        }
+        tick += 1;
+    }
+}
+
+// e.g. "01:02:03.45", for the hover text and the screen reader label.
+fn format_time(time: f32) -> String {
+    let hours = time as u32 / 3600;
+    let mins = (time as u32 / 60) % 60;
+    let secs = time % 60.0;
+    format!("{hours:02}:{mins:02}:{secs:05.2}")
+}
+
+// Shorter than `format_time`, only showing the precision the current tick spacing needs.
+fn ruler_label(time: f32, step: f32) -> String {
+    let hours = time as u32 / 3600;
+    let mins = (time as u32 / 60) % 60;
+    let secs = time % 60.0;
+    if step < 1.0 {
+        format!("{mins}:{secs:04.1}")
+    } else if hours > 0 {
+        format!("{hours}:{mins:02}:{:02}", secs as u32)
+    } else {
+        format!("{mins}:{:02}", secs as u32)
+    }
+}
+
+// How close (in points) the pointer has to be to a handle to grab it.
+const HANDLE_GRAB_WIDTH: f32 = 8.0;
+const HANDLE_WIDTH: f32 = 6.0;
+// How close (in points) a dragged handle has to get to a marker to snap to it.
+const SNAP_DISTANCE: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum DragTarget {
+    Start,
+    End,
+    Selection,
+    Playhead,
+}
+
+type BackgroundPainter<'a> = Box<dyn FnOnce(&egui::Painter, egui::Rect, RangeInclusive<f32>) + 'a>;
+
+pub struct TrimTimeline<'a> {
+    start: &'a mut f32,
+    end: &'a mut f32,
+    duration: f32,
+    view: &'a mut TimelineView,
+    playhead: Option<&'a mut f32>,
+    lock_end: bool,
+    background: Option<BackgroundPainter<'a>>,
+    markers: &'a [f32],
+    snap_to_markers: bool,
+    regions: &'a [(RangeInclusive<f32>, Color32)],
+}
+
+pub struct TrimTimelineOutput {
+    pub response: egui::Response,
+    // Where the track was drawn, for lining other lanes up with it.
+    pub track_rect: egui::Rect,
+    // Set on the frame a drag that moved the start/end handle is let go of.
+    pub start_released: bool,
+    pub end_released: bool,
+}
+
+impl<'a> TrimTimeline<'a> {
+    pub fn new(start: &'a mut f32, end: &'a mut f32, duration: f32, view: &'a mut TimelineView) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+            view,
+            playhead: None,
+            lock_end: false,
+            background: None,
+            markers: &[],
+            snap_to_markers: false,
+            regions: &[],
+        }
+    }
+
+    // Shows a playhead, which can be moved by clicking or dragging on the track outside the selection.
+    pub fn playhead(mut self, playhead: &'a mut f32) -> Self {
+        self.playhead = Some(playhead);
+        self
+    }
+
+    // Keeps the end handle pinned to the end of the video.
+    pub fn lock_end(mut self, lock_end: bool) -> Self {
+        self.lock_end = lock_end;
+        self
+    }
+
+    // Paints something (thumbnails, ...) on the track under the selection. Gets the track rect and the visible time range.
+    pub fn background(mut self, paint: impl FnOnce(&egui::Painter, egui::Rect, RangeInclusive<f32>) + 'a) -> Self {
+        self.background = Some(Box::new(paint));
+        self
+    }
+
+    // Marks points in time (scene cuts, ...) on the track.
+    pub fn markers(mut self, markers: &'a [f32]) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    // Makes the start and end handles snap to the markers when they're dragged close to one.
+    pub fn snap_to_markers(mut self, snap: bool) -> Self {
+        self.snap_to_markers = snap;
+        self
+    }
+
+    // Shades stretches of time (silences, ...) along the bottom of the track, each in its own colour.
+    pub fn regions(mut self, regions: &'a [(RangeInclusive<f32>, Color32)]) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    pub fn show(self, ui: &mut egui::Ui) -> TrimTimelineOutput {
+        let Self {
+            start,
+            end,
+            duration,
+            view,
+            mut playhead,
+            lock_end,
+            background,
+            markers,
+            snap_to_markers,
+            regions,
+        } = self;
+        let id = ui.id().with("trim_timeline");
+        let width = ui.available_width();
+        let (ruler_rect, ruler_response) = ui.allocate_exact_size(egui::vec2(width, 16.0), egui::Sense::click_and_drag());
+        let (track_rect, _) = ui.allocate_exact_size(egui::vec2(width, 40.0), egui::Sense::hover());
+        let mut response = ui.interact(track_rect, id, egui::Sense::click_and_drag());
+
+        // Mouse wheel zooms around the cursor, horizontal scrolling (or Shift + wheel) and dragging the ruler pan.
+        let hover_pos = ui.input(|i| i.pointer.hover_pos());
+        if let Some(pointer) = hover_pos.filter(|p| ruler_rect.union(track_rect).contains(*p)) {
+            let scroll = ui.input(|i| i.smooth_scroll_delta);
+            if scroll.y != 0.0 {
+                view.zoom((scroll.y * 0.005).exp(), view.x_to_time(track_rect, pointer.x), duration);
+            }
+            if scroll.x != 0.0 {
+                view.pan(-scroll.x * view.seconds_per_pixel(track_rect), duration);
+            }
+        }
+        let ruler_response = ruler_response
+            .on_hover_and_drag_cursor(egui::CursorIcon::Grab)
+            .on_hover_text("Scroll to zoom, drag to pan, double-click to show the whole video");
+        if ruler_response.dragged() {
+            view.pan(-ruler_response.drag_delta().x * view.seconds_per_pixel(track_rect), duration);
+        }
+        if ruler_response.double_clicked() {
+            *view = TimelineView::full(duration);
+        }
+
+        if lock_end {
+            *end = duration;
+        }
+        let start_x = view.time_to_x(track_rect, *start);
+        let end_x = view.time_to_x(track_rect, *end);
+        let has_playhead = playhead.is_some();
+        let target_at = |x: f32| {
+            if (x - start_x).abs() <= HANDLE_GRAB_WIDTH && (x <= end_x || lock_end) {
+                Some(DragTarget::Start)
+            } else if (x - end_x).abs() <= HANDLE_GRAB_WIDTH && !lock_end {
+                Some(DragTarget::End)
+            } else if x > start_x && x < end_x && !lock_end {
+                Some(DragTarget::Selection)
+            } else if has_playhead {
+                Some(DragTarget::Playhead)
+            } else {
+                None
+            }
+        };
+
+        if let Some(pos) = response.hover_pos() {
+            match target_at(pos.x) {
+                Some(DragTarget::Start | DragTarget::End) => ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal),
+                Some(DragTarget::Selection) => ui.ctx().set_cursor_icon(egui::CursorIcon::Grab),
+                _ => {}
+            }
+        }
+
+        // Handles move by the time under the mouse, so they get more precise the further the timeline is zoomed in.
+        let seconds_per_pixel = view.seconds_per_pixel(track_rect);
+        // While snapping, the handle's position without the snap is kept separately so it can be dragged off a marker again.
+        let unsnapped_id = id.with("unsnapped");
+        if response.drag_started() {
+            let target = response.interact_pointer_pos().and_then(|p| target_at(p.x));
+            let unsnapped = if target == Some(DragTarget::End) { *end } else { *start };
+            ui.data_mut(|d| {
+                d.insert_temp(id, target);
+                d.insert_temp(unsnapped_id, unsnapped);
+            });
+        }
+        let target = ui.data(|d| d.get_temp::<Option<DragTarget>>(id)).flatten();
+        let snap = |time: f32| {
+            let x = view.time_to_x(track_rect, time);
+            markers
+                .iter()
+                .copied()
+                .filter(|marker| snap_to_markers && (view.time_to_x(track_rect, *marker) - x).abs() <= SNAP_DISTANCE)
+                .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+                .unwrap_or(time)
+        };
+        if response.dragged() {
+            let delta = response.drag_delta().x * seconds_per_pixel;
+            let unsnapped = ui.data(|d| d.get_temp::<f32>(unsnapped_id)).unwrap_or_default() + delta;
+            match target {
+                Some(DragTarget::Start) => {
+                    let unsnapped = unsnapped.clamp(0.0, *end);
+                    ui.data_mut(|d| d.insert_temp(unsnapped_id, unsnapped));
+                    *start = snap(unsnapped).clamp(0.0, *end);
+                }
+                Some(DragTarget::End) => {
+                    let unsnapped = unsnapped.clamp(*start, duration);
+                    ui.data_mut(|d| d.insert_temp(unsnapped_id, unsnapped));
+                    *end = snap(unsnapped).clamp(*start, duration);
+                }
+                Some(DragTarget::Selection) => {
+                    let delta = delta.clamp(-*start, duration - *end);
+                    *start += delta;
+                    *end += delta;
+                }
+                Some(DragTarget::Playhead) => {
+                    if let (Some(playhead), Some(pos)) = (playhead.as_deref_mut(), response.interact_pointer_pos()) {
+                        *playhead = view.x_to_time(track_rect, pos.x).clamp(0.0, duration);
+                    }
+                }
+                None => {}
+            }
+            if target.is_some() {
+                response.mark_changed();
+            }
+        }
+        let (start_released, end_released) = if response.drag_stopped() {
+            ui.data_mut(|d| d.remove::<Option<DragTarget>>(id));
+            match target {
+                Some(DragTarget::Start) => (true, false),
+                Some(DragTarget::End) => (false, true),
+                Some(DragTarget::Selection) => (true, true),
+                _ => (false, false),
+            }
+        } else {
+            (false, false)
+        };
+        if response.clicked() {
+            if let (Some(playhead), Some(pos)) = (playhead.as_deref_mut(), response.interact_pointer_pos()) {
+                *playhead = view.x_to_time(track_rect, pos.x).clamp(0.0, duration);
+                response.mark_changed();
+            }
+        }
+        if let Some(pos) = response.hover_pos() {
+            let time = view.x_to_time(track_rect, pos.x).clamp(0.0, duration);
+            response = response.on_hover_text_at_pointer(format_time(time));
+        }
+        let (start_value, end_value) = (*start, *end);
+        response.widget_info(|| {
+            WidgetInfo::labeled(
+                WidgetType::Slider,
+                format!("Trim range, from {} to {}", format_time(start_value), format_time(end_value)),
+            )
+        });
+
+        if ui.is_rect_visible(track_rect) {
+            paint_ruler(&ui.painter_at(ruler_rect), ruler_rect, view);
+
+            let painter = ui.painter_at(track_rect);
+            let start_x = view.time_to_x(track_rect, *start);
+            let end_x = view.time_to_x(track_rect, *end);
+            let selection = egui::Rect::from_x_y_ranges(start_x..=end_x, track_rect.y_range());
+            painter.rect(track_rect, 0.0, Color32::DARK_GRAY, egui::Stroke::new(1.0, Color32::DARK_GRAY));
+            if let Some(background) = background {
+                background(&painter, track_rect, view.range());
+                // Dim what gets trimmed off instead of covering the background with the selection.
+                let trimmed_off = Color32::from_black_alpha(170);
+                painter.rect_filled(egui::Rect::from_x_y_ranges(track_rect.left()..=start_x, track_rect.y_range()), 0.0, trimmed_off);
+                painter.rect_filled(egui::Rect::from_x_y_ranges(end_x..=track_rect.right(), track_rect.y_range()), 0.0, trimmed_off);
+                painter.rect_stroke(selection, 0.0, egui::Stroke::new(2.0, Color32::LIGHT_YELLOW));
+            } else {
+                painter.rect_filled(selection, 0.0, Color32::LIGHT_YELLOW.gamma_multiply(0.6));
+            }
+            let band = track_rect.bottom() - 8.0..=track_rect.bottom();
+            for (range, color) in regions {
+                let x_range = view.time_to_x(track_rect, *range.start())..=view.time_to_x(track_rect, *range.end());
+                let tint = egui::Rect::from_x_y_ranges(x_range.clone(), track_rect.y_range());
+                painter.rect_filled(tint, 0.0, color.gamma_multiply(0.15));
+                painter.rect_filled(egui::Rect::from_x_y_ranges(x_range, band.clone()), 0.0, color.gamma_multiply(0.8));
+            }
+            let marker_stroke = egui::Stroke::new(1.0, Color32::from_rgb(80, 200, 255).gamma_multiply(0.7));
+            for marker in markers.iter().filter(|m| view.range().contains(m)) {
+                painter.vline(view.time_to_x(track_rect, *marker), track_rect.y_range(), marker_stroke);
+            }
+
+            // The handle under the mouse (or being dragged) is highlighted.
+            let active = if response.dragged() { target } else { response.hover_pos().and_then(|p| target_at(p.x)) };
+            let handle_color = |handle| if active == Some(handle) { Color32::WHITE } else { Color32::from_hex("#c8c8c8").unwrap() };
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(start_x..=(start_x + HANDLE_WIDTH), track_rect.y_range()),
+                2.0,
+                handle_color(DragTarget::Start),
+            );
+            if !lock_end {
+                painter.rect_filled(
+                    egui::Rect::from_x_y_ranges((end_x - HANDLE_WIDTH)..=end_x, track_rect.y_range()),
+                    2.0,
+                    handle_color(DragTarget::End),
+                );
+            }
+
+            if let Some(playhead) = playhead {
+                let x = view.time_to_x(track_rect, *playhead);
+                painter.vline(x, track_rect.y_range(), egui::Stroke::new(2.0, Color32::from_hex("#ff5050").unwrap()));
+            }
+        }
+
+        TrimTimelineOutput {
+            response,
+            track_rect,
+            start_released,
+            end_released,
+        }
+    }
+}
+
+impl egui::Widget for TrimTimeline<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        self.show(ui).response
+    }
+}
@@ -0,0 +1,691 @@
+// Builds the ffmpeg arguments for a trim export.
+//
+//     let args = TrimBuilder::new(input, output).range(start, Some(end)).video_filter("scale=-2:720").build();
+//     let args = TrimBuilder::new(input, output).range(start, Some(end)).speed(Speed { factor: 2.0, reverse: true }).build();
+//
+// Stream copy is used whenever it can be. Anything that has to touch the decoded frames (a video filter or
+// a fade) switches the export over to re-encoding, since filters can't be applied to copied packets. The same goes
+// for the audio, separately: changing volumes or mixing re-encodes the audio but can still copy the video.
+
+use crate::{
+    audio::{AudioPlan, LOUDNESS_TARGET},
+    container,
+    hwaccel::VideoEncoder,
+    num_to_time,
+    subtitles::{SubtitlePlan, SubtitleSource},
+};
+
+// Fade lengths in seconds, 0.0 for none. They're timed against the clip itself, so they land on its
+// boundaries whatever part of the source it was cut from.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Fades {
+    pub video_in: f32,
+    pub video_out: f32,
+    pub audio_in: f32,
+    pub audio_out: f32,
+}
+
+impl Fades {
+    pub fn any(&self) -> bool {
+        self.video_in > 0.0 || self.video_out > 0.0 || self.audio_in > 0.0 || self.audio_out > 0.0
+    }
+
+    // fade/afade filters for a clip `length` seconds long. A fade in and out that would overlap are
+    // shortened to meet in the middle.
+    fn filters(filter: &str, fade_in: f32, fade_out: f32, length: f32) -> Vec<String> {
+        let fade_in = fade_in.min(length / 2.0);
+        let fade_out = fade_out.min(length / 2.0);
+        let mut filters = vec![];
+        if fade_in > 0.0 {
+            filters.push(format!("{filter}=t=in:st=0:d={fade_in}"));
+        }
+        if fade_out > 0.0 {
+            filters.push(format!("{filter}=t=out:st={}:d={fade_out}", length - fade_out));
+        }
+        filters
+    }
+}
+
+// Playback speed and direction of the exported clip.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Speed {
+    // 2.0 plays twice as fast.
+    pub factor: f32,
+    pub reverse: bool,
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self { factor: 1.0, reverse: false }
+    }
+}
+
+impl Speed {
+    pub const PRESETS: [f32; 8] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 4.0];
+
+    pub fn is_changed(&self) -> bool {
+        self.factor != 1.0 || self.reverse
+    }
+
+    // How long a clip `clip_length` seconds long in the source ends up.
+    pub fn output_length(&self, clip_length: f32) -> f32 {
+        clip_length / self.factor
+    }
+
+    // `reverse` keeps the whole clip in memory, so it only works for short clips.
+    fn video_filters(&self) -> Vec<String> {
+        let mut filters = vec![];
+        if self.reverse {
+            filters.push("reverse".to_owned());
+        }
+        if self.factor != 1.0 {
+            filters.push(format!("setpts=PTS/{}", self.factor));
+        }
+        filters
+    }
+
+    fn audio_filters(&self) -> Vec<String> {
+        let mut filters = vec![];
+        if self.reverse {
+            filters.push("areverse".to_owned());
+        }
+        // atempo only goes from 0.5 to 2, so bigger changes are made of several.
+        let mut factor = self.factor;
+        while factor > 2.0 {
+            filters.push("atempo=2".to_owned());
+            factor /= 2.0;
+        }
+        while factor < 0.5 {
+            filters.push("atempo=0.5".to_owned());
+            factor /= 0.5;
+        }
+        if factor != 1.0 {
+            filters.push(format!("atempo={factor}"));
+        }
+        filters
+    }
+}
+
+// Everything in the source goes into the output, apart from the streams the container can't hold.
+pub struct KeepAll {
+    // Stream indexes to leave out.
+    pub dropped: Vec<usize>,
+}
+
+pub struct TrimBuilder {
+    input: String,
+    output: String,
+    start: f32,
+    // None trims to the end of the video.
+    end: Option<f32>,
+    reencode: bool,
+    overwrite: bool,
+    video_filters: Vec<String>,
+    // None leaves the stream selection to ffmpeg.
+    audio: Option<AudioPlan>,
+    keep_all: Option<KeepAll>,
+    fades: Fades,
+    // Length of the trimmed section of the source, which the fade outs are timed from.
+    clip_length: f32,
+    speed: Speed,
+    subtitles: Option<SubtitlePlan>,
+    encoder: VideoEncoder,
+    hardware_decode: bool,
+    // Parts of the source to keep, empty for the whole range.
+    segments: Vec<(f32, f32)>,
+    // ffmetadata file with the chapters already moved to the clip's timeline. None leaves the clip without chapters.
+    chapters: Option<String>,
+}
+
+impl TrimBuilder {
+    pub fn new(input: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            start: 0.0,
+            end: None,
+            reencode: false,
+            overwrite: false,
+            video_filters: vec![],
+            audio: None,
+            keep_all: None,
+            fades: Fades::default(),
+            clip_length: 0.0,
+            speed: Speed::default(),
+            subtitles: None,
+            encoder: VideoEncoder::Software,
+            hardware_decode: false,
+            segments: vec![],
+            chapters: None,
+        }
+    }
+
+    pub fn range(mut self, start: f32, end: Option<f32>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    pub fn reencode(mut self, reencode: bool) -> Self {
+        self.reencode = reencode;
+        self
+    }
+
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    // Appended to the `-vf` chain in the order they're added.
+    pub fn video_filter(mut self, filter: impl Into<String>) -> Self {
+        self.video_filters.push(filter.into());
+        self
+    }
+
+    pub fn audio(mut self, plan: AudioPlan) -> Self {
+        self.audio = Some(plan);
+        self
+    }
+
+    // Only used for stream copies, and takes over from `audio` when it is.
+    pub fn keep_all_streams(mut self, keep_all: KeepAll) -> Self {
+        self.keep_all = Some(keep_all);
+        self
+    }
+
+    // Fades need the length of the clip, which the builder can't work out when trimming to the end.
+    pub fn fades(mut self, fades: Fades, clip_length: f32) -> Self {
+        self.fades = fades;
+        self.clip_length = clip_length;
+        self
+    }
+
+    pub fn speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    // Soft subtitles are only added along with an `audio` plan, since that's when the streams are mapped by hand.
+    // Burned in ones work either way. "Keep everything" keeps the video's own subtitles instead.
+    pub fn subtitles(mut self, plan: SubtitlePlan) -> Self {
+        self.subtitles = Some(plan);
+        self
+    }
+
+    fn burns_in_subtitles(&self) -> bool {
+        self.subtitles.as_ref().is_some_and(|s| s.burn_in)
+    }
+
+    pub fn reencodes(&self) -> bool {
+        self.reencode
+            || !self.video_filters.is_empty()
+            || self.fades.any()
+            || self.speed.is_changed()
+            || self.burns_in_subtitles()
+            || !self.segments.is_empty()
+    }
+
+    // Keeps only these parts (in the source's time, inside the range) and joins them back to back.
+    // Frames are picked out with select/aselect, which needs a re-encode.
+    pub fn segments(mut self, segments: Vec<(f32, f32)>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    pub fn chapters(mut self, chapters: Option<String>) -> Self {
+        self.chapters = chapters;
+        self
+    }
+
+    // Only used when re-encoding. `hardware_decode` also decodes on the encoder's hardware.
+    pub fn video_encoder(mut self, encoder: VideoEncoder, hardware_decode: bool) -> Self {
+        self.encoder = encoder;
+        self.hardware_decode = hardware_decode;
+        self
+    }
+
+    // The hardware encoders all make H.264, which WebM can't hold, so those exports stay in software.
+    pub fn uses_hardware(&self) -> bool {
+        let extension = container::extension(&self.output);
+        self.encoder.is_hardware() && self.reencodes() && (container::is_mp4_family(&extension) || extension == "mkv")
+    }
+
+    fn keeps_all(&self) -> bool {
+        self.keep_all.is_some() && !self.reencodes()
+    }
+
+    // Subtitles that go in as their own track, with the codec they're stored as. Subtitles the container
+    // can't hold are left out rather than failing the export.
+    fn soft_subtitles(&self) -> Option<(&SubtitlePlan, &'static str)> {
+        let plan = self
+            .subtitles
+            .as_ref()
+            .filter(|s| !s.burn_in && self.audio.is_some() && !self.keeps_all())?;
+        Some((plan, plan.soft_codec(&container::extension(&self.output))?))
+    }
+
+    // Length of what's left of the clip once the segments are joined.
+    fn kept_length(&self) -> f32 {
+        if self.segments.is_empty() {
+            self.clip_length
+        } else {
+            self.segments.iter().map(|(from, to)| to - from).sum()
+        }
+    }
+
+    // select/aselect expression that keeps the segments, on the clip's timeline.
+    fn segment_expression(&self) -> String {
+        let parts: Vec<String> = self
+            .segments
+            .iter()
+            .map(|(from, to)| format!("between(t\\,{}\\,{})", from - self.start, to - self.start))
+            .collect();
+        parts.join("+")
+    }
+
+    // Subtitles are burned in on the clip as it comes out of the other filters, but before the segments are joined and
+    // the speed changes since they need the source's timestamps. The fades come last so they're timed against the clip
+    // as it ends up.
+    fn all_video_filters(&self) -> Vec<String> {
+        let mut filters = self.video_filters.clone();
+        if let Some(subtitles) = self.subtitles.as_ref().filter(|s| s.burn_in) {
+            filters.push(subtitles.burn_in_filter(&self.input, self.start));
+        }
+        if !self.segments.is_empty() {
+            filters.push(format!("select={},setpts=N/FRAME_RATE/TB", self.segment_expression()));
+        }
+        filters.extend(self.speed.video_filters());
+        let length = self.speed.output_length(self.kept_length());
+        filters.extend(Fades::filters("fade", self.fades.video_in, self.fades.video_out, length));
+        if let (true, Some(upload)) = (self.uses_hardware(), self.encoder.upload_filter()) {
+            filters.push(upload.to_owned());
+        }
+        filters
+    }
+
+    // Filters every output audio track goes through, after its own volume.
+    fn audio_filters(&self) -> Vec<String> {
+        let mut filters = vec![];
+        if !self.segments.is_empty() {
+            filters.push(format!("aselect={},asetpts=N/SR/TB", self.segment_expression()));
+        }
+        filters.extend(self.speed.audio_filters());
+        let length = self.speed.output_length(self.kept_length());
+        filters.extend(Fades::filters("afade", self.fades.audio_in, self.fades.audio_out, length));
+        filters
+    }
+
+    pub fn build(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![];
+        if self.overwrite {
+            args.push("-y".into());
+        }
+        if self.uses_hardware() {
+            args.extend(self.encoder.input_args(self.hardware_decode));
+        }
+        // Seeking on the input jumps straight to the keyframe before the start. A copy starts from there, which is
+        // what makes it fast; a re-encode decodes from there and drops the frames before the start, so it still cuts
+        // on the exact frame. Either way the clip's timestamps start at 0, which the fades rely on.
+        args.extend(["-ss".into(), num_to_time(self.start)]);
+        if let Some(end) = self.end {
+            args.extend(["-to".into(), num_to_time(end)]);
+        }
+        args.extend(["-i".into(), self.input.clone()]);
+        let mut inputs = 1;
+        // The subtitle file is seeked the same way so its timestamps line up with the clip.
+        if let Some((
+            SubtitlePlan {
+                source: SubtitleSource::File(path),
+                ..
+            },
+            _,
+        )) = self.soft_subtitles()
+        {
+            args.extend(["-ss".into(), num_to_time(self.start)]);
+            if let Some(end) = self.end {
+                args.extend(["-to".into(), num_to_time(end)]);
+            }
+            args.extend(["-i".into(), path.clone()]);
+            inputs += 1;
+        }
+        match &self.chapters {
+            Some(chapters) => args.extend(["-i".into(), chapters.clone(), "-map_chapters".into(), inputs.to_string()]),
+            None => args.extend(["-map_chapters".into(), "-1".into()]),
+        }
+        if !self.reencodes() {
+            self.push_stream_args(&mut args);
+            args.extend(["-c".into(), "copy".into()]);
+            self.push_codecs(&mut args);
+        } else {
+            self.push_stream_args(&mut args);
+            let video_filters = self.all_video_filters();
+            if !video_filters.is_empty() {
+                args.extend(["-vf".into(), video_filters.join(",")]);
+            }
+            if self.uses_hardware() {
+                args.extend(self.encoder.output_args());
+            }
+            self.push_codecs(&mut args);
+            args.extend(["-async".into(), "1".into()]);
+        }
+        args.push(self.output.clone());
+        args
+    }
+
+    // `-map`s for the video, subtitles and the chosen audio tracks, plus whatever filters the audio needs.
+    fn push_stream_args(&self, args: &mut Vec<String>) {
+        if let (Some(keep_all), true) = (&self.keep_all, self.keeps_all()) {
+            args.extend(["-map".into(), "0".into()]);
+            for index in &keep_all.dropped {
+                args.extend(["-map".into(), format!("-0:{index}")]);
+            }
+            // Global tags like the creation time. Stream tags (language, rotation) already go along with their streams.
+            args.extend(["-map_metadata".into(), "0".into()]);
+            // Otherwise MP4 only keeps the handful of tags it has its own atoms for.
+            if container::is_mp4_family(&container::extension(&self.output)) {
+                args.extend(["-movflags".into(), "use_metadata_tags".into()]);
+            }
+            return;
+        }
+        let audio_filters = self.audio_filters();
+        let Some(plan) = &self.audio else {
+            if !audio_filters.is_empty() {
+                args.extend(["-af".into(), audio_filters.join(",")]);
+            }
+            return;
+        };
+        args.extend(["-map".into(), "0:v:0?".into()]);
+        if let Some((subtitles, _)) = self.soft_subtitles() {
+            match &subtitles.source {
+                SubtitleSource::Stream(number) => args.extend(["-map".into(), format!("0:s:{number}")]),
+                SubtitleSource::File(_) => args.extend(["-map".into(), "1:0".into()]),
+            }
+        }
+        if plan.tracks.is_empty() {
+            args.push("-an".into());
+            return;
+        }
+        // Applied to every output track, after its volume.
+        let mut after = vec![];
+        if plan.normalize {
+            // loudnorm works at 192kHz internally and outputs that too, so it's brought back down afterwards.
+            after.extend([format!("loudnorm=I={LOUDNESS_TARGET}:TP=-1"), "aresample=48000".to_owned()]);
+        }
+        after.extend(audio_filters);
+        if plan.mix_down && plan.tracks.len() > 1 {
+            let mut graph = String::new();
+            for (i, (stream, volume)) in plan.tracks.iter().enumerate() {
+                graph.push_str(&format!("[0:a:{stream}]volume={volume}[a{i}];"));
+            }
+            let inputs: String = (0..plan.tracks.len()).map(|i| format!("[a{i}]")).collect();
+            // normalize=0 stops amix from turning every track down to make room for the others.
+            let mut chain = vec![format!("{inputs}amix=inputs={}:duration=longest:normalize=0", plan.tracks.len())];
+            chain.extend(after);
+            graph.push_str(&format!("{}[mixed]", chain.join(",")));
+            args.extend(["-filter_complex".into(), graph, "-map".into(), "[mixed]".into()]);
+        } else {
+            for (stream, _) in &plan.tracks {
+                args.extend(["-map".into(), format!("0:a:{stream}")]);
+            }
+            for (i, (_, volume)) in plan.tracks.iter().enumerate() {
+                let mut chain = vec![];
+                if *volume != 1.0 {
+                    chain.push(format!("volume={volume}"));
+                }
+                chain.extend(after.iter().cloned());
+                if !chain.is_empty() {
+                    args.extend([format!("-filter:a:{i}"), chain.join(",")]);
+                }
+            }
+        }
+    }
+
+    // These come after any `-c copy` so they win for their streams.
+    fn push_codecs(&self, args: &mut Vec<String>) {
+        if self.keeps_all() {
+            return;
+        }
+        if self.audio.as_ref().is_some_and(AudioPlan::needs_reencode) {
+            args.extend(["-c:a".into(), "aac".into(), "-b:a".into(), "192k".into()]);
+        }
+        if let Some((_, codec)) = self.soft_subtitles() {
+            args.extend(["-c:s".into(), codec.into()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSettings;
+
+    #[test]
+    fn software_copy() {
+        let args = TrimBuilder::new("in.mp4", "out.mp4")
+            .range(1.5, Some(10.0))
+            .video_encoder(VideoEncoder::Software, true)
+            .build();
+        assert_eq!(
+            args,
+            [
+                "-ss",
+                "00:00:01.50",
+                "-to",
+                "00:00:10.00",
+                "-i",
+                "in.mp4",
+                "-map_chapters",
+                "-1",
+                "-c",
+                "copy",
+                "out.mp4"
+            ]
+        );
+    }
+
+    #[test]
+    fn software_reencode() {
+        let args = TrimBuilder::new("in.mp4", "out.mp4")
+            .range(0.0, None)
+            .overwrite(true)
+            .video_filter("scale=-2:720")
+            .video_encoder(VideoEncoder::Software, true)
+            .build();
+        assert_eq!(
+            args,
+            [
+                "-y",
+                "-ss",
+                "00:00:00.00",
+                "-i",
+                "in.mp4",
+                "-map_chapters",
+                "-1",
+                "-vf",
+                "scale=-2:720",
+                "-async",
+                "1",
+                "out.mp4"
+            ]
+        );
+    }
+
+    #[test]
+    fn hardware_only_when_reencoding_to_h264_containers() {
+        let copy = TrimBuilder::new("in.mp4", "out.mp4").video_encoder(VideoEncoder::Vaapi, false);
+        assert!(!copy.uses_hardware());
+        let webm = TrimBuilder::new("in.mp4", "out.webm")
+            .reencode(true)
+            .video_encoder(VideoEncoder::Nvenc, false);
+        assert!(!webm.uses_hardware());
+        assert!(!webm.build().iter().any(|arg| arg == "-c:v" || arg == "-hwaccel"));
+
+        let args = TrimBuilder::new("in.mp4", "out.mp4")
+            .reencode(true)
+            .video_encoder(VideoEncoder::Vaapi, false)
+            .build();
+        assert_eq!(args[..2], ["-vaapi_device", "/dev/dri/renderD128"]);
+        assert!(args.windows(2).any(|pair| pair == ["-vf", "format=nv12,hwupload"]));
+        assert!(args.windows(2).any(|pair| pair == ["-c:v", "h264_vaapi"]));
+    }
+
+    // Everything after the input and chapter options, for a stream copy of in.mkv with this audio plan.
+    fn audio_args(configure: impl FnOnce(&mut AudioSettings)) -> Vec<String> {
+        let mut settings = AudioSettings::for_streams(3);
+        configure(&mut settings);
+        let args = TrimBuilder::new("in.mkv", "out.mkv").audio(settings.plan()).build();
+        assert_eq!(args[..6], ["-ss", "00:00:00.00", "-i", "in.mkv", "-map_chapters", "-1"]);
+        args[6..].to_vec()
+    }
+
+    #[test]
+    fn all_tracks_are_copied() {
+        assert_eq!(
+            audio_args(|_| {}),
+            ["-map", "0:v:0?", "-map", "0:a:0", "-map", "0:a:1", "-map", "0:a:2", "-c", "copy", "out.mkv"]
+        );
+    }
+
+    #[test]
+    fn per_track_volume() {
+        let args = audio_args(|settings| {
+            settings.tracks[0].mute = true;
+            settings.tracks[1].include = false;
+            settings.tracks[2].volume = 0.5;
+        });
+        assert_eq!(
+            args,
+            [
+                "-map",
+                "0:v:0?",
+                "-map",
+                "0:a:0",
+                "-map",
+                "0:a:2",
+                "-filter:a:0",
+                "volume=0",
+                "-filter:a:1",
+                "volume=0.5",
+                "-c",
+                "copy",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "192k",
+                "out.mkv"
+            ]
+        );
+    }
+
+    #[test]
+    fn mixdown_with_loudness_normalization() {
+        let args = audio_args(|settings| {
+            settings.tracks[2].include = false;
+            settings.mix_down = true;
+            settings.normalize = true;
+        });
+        assert_eq!(
+            args,
+            [
+                "-map",
+                "0:v:0?",
+                "-filter_complex",
+                "[0:a:0]volume=1[a0];[0:a:1]volume=1[a1];[a0][a1]amix=inputs=2:duration=longest:normalize=0,loudnorm=I=-23:TP=-1,aresample=48000[mixed]",
+                "-map",
+                "[mixed]",
+                "-c",
+                "copy",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "192k",
+                "out.mkv"
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_without_mixing() {
+        let args = audio_args(|settings| {
+            settings.tracks.truncate(1);
+            settings.normalize = true;
+        });
+        assert_eq!(
+            args,
+            [
+                "-map",
+                "0:v:0?",
+                "-map",
+                "0:a:0",
+                "-filter:a:0",
+                "loudnorm=I=-23:TP=-1,aresample=48000",
+                "-c",
+                "copy",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "192k",
+                "out.mkv"
+            ]
+        );
+    }
+
+    #[test]
+    fn no_tracks_drops_the_audio() {
+        let args = audio_args(|settings| settings.tracks.iter_mut().for_each(|track| track.include = false));
+        assert_eq!(args, ["-map", "0:v:0?", "-an", "-c", "copy", "out.mkv"]);
+    }
+
+    #[test]
+    fn atempo_chains_stay_in_range() {
+        let speed = |factor| Speed { factor, reverse: false };
+        assert_eq!(speed(0.25).audio_filters(), ["atempo=0.5", "atempo=0.5"]);
+        assert_eq!(speed(0.5).audio_filters(), ["atempo=0.5"]);
+        assert_eq!(speed(1.5).audio_filters(), ["atempo=1.5"]);
+        assert_eq!(speed(2.0).audio_filters(), ["atempo=2"]);
+        assert_eq!(speed(4.0).audio_filters(), ["atempo=2", "atempo=2"]);
+        assert!(speed(1.0).audio_filters().is_empty());
+        assert!(speed(1.0).video_filters().is_empty());
+        assert_eq!(speed(0.25).video_filters(), ["setpts=PTS/0.25"]);
+        assert_eq!(speed(4.0).video_filters(), ["setpts=PTS/4"]);
+    }
+
+    #[test]
+    fn reverse_comes_before_the_speed_change() {
+        let reversed = |factor| Speed { factor, reverse: true };
+        assert_eq!(reversed(0.25).audio_filters(), ["areverse", "atempo=0.5", "atempo=0.5"]);
+        assert_eq!(reversed(0.5).audio_filters(), ["areverse", "atempo=0.5"]);
+        assert_eq!(reversed(2.0).audio_filters(), ["areverse", "atempo=2"]);
+        assert_eq!(reversed(4.0).audio_filters(), ["areverse", "atempo=2", "atempo=2"]);
+        assert_eq!(reversed(1.0).audio_filters(), ["areverse"]);
+        assert_eq!(reversed(2.0).video_filters(), ["reverse", "setpts=PTS/2"]);
+        assert_eq!(reversed(1.0).video_filters(), ["reverse"]);
+    }
+
+    #[test]
+    fn overlapping_fades_meet_in_the_middle() {
+        assert_eq!(Fades::filters("fade", 3.0, 3.0, 2.0), ["fade=t=in:st=0:d=1", "fade=t=out:st=1:d=1"]);
+        assert_eq!(Fades::filters("afade", 0.5, 5.0, 4.0), ["afade=t=in:st=0:d=0.5", "afade=t=out:st=2:d=2"]);
+        assert_eq!(Fades::filters("fade", 0.0, 1.5, 10.0), ["fade=t=out:st=8.5:d=1.5"]);
+        assert!(Fades::filters("fade", 0.0, 0.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn fades_are_timed_on_the_sped_up_clip() {
+        let fades = Fades {
+            video_in: 1.0,
+            video_out: 1.0,
+            audio_in: 0.0,
+            audio_out: 2.0,
+        };
+        let args = TrimBuilder::new("in.mkv", "out.mkv")
+            .range(10.0, Some(20.0))
+            .speed(Speed { factor: 2.0, reverse: false })
+            .fades(fades, 10.0)
+            .build();
+        let value = |option: &str| &args[args.iter().position(|arg| arg == option).unwrap() + 1];
+        // The 10s clip comes out 5s long, so the fade outs end there rather than at 10s.
+        assert_eq!(value("-vf"), "setpts=PTS/2,fade=t=in:st=0:d=1,fade=t=out:st=4:d=1");
+        assert_eq!(value("-af"), "atempo=2,afade=t=out:st=3:d=2");
+    }
+}
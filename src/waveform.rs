@@ -0,0 +1,163 @@
+// Audio waveform shown in a lane under the scrubber.
+//
+// The audio is decoded once by ffmpeg at a low sample rate on a background thread and boiled down to
+// min/max peaks, which are cached on disk so opening the same video again is instant.
+
+use std::{
+    fs,
+    io::Read,
+    ops::RangeInclusive,
+    process::Stdio,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, Color32};
+
+use crate::{cache, hidden_command};
+
+const SAMPLE_RATE: usize = 8000;
+pub const PEAKS_PER_SECOND: usize = 100;
+const SAMPLES_PER_PEAK: usize = SAMPLE_RATE / PEAKS_PER_SECOND;
+
+// Anything quieter than this (about -40 dB) counts as silence.
+const SILENCE_THRESHOLD: f32 = 0.01;
+
+pub struct Peaks {
+    // min/max sample of every 1/PEAKS_PER_SECOND of audio, in -1.0..=1.0
+    pub peaks: Vec<[f32; 2]>,
+}
+
+impl Peaks {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.peaks.iter().flat_map(|[min, max]| min.to_le_bytes().into_iter().chain(max.to_le_bytes())).collect()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let peaks = bytes
+            .chunks_exact(8)
+            .map(|c| [f32::from_le_bytes([c[0], c[1], c[2], c[3]]), f32::from_le_bytes([c[4], c[5], c[6], c[7]])])
+            .collect();
+        Self { peaks }
+    }
+
+    // min/max over the peaks covering start..end (in seconds)
+    fn range(&self, start: f32, end: f32) -> Option<[f32; 2]> {
+        let first = (start * PEAKS_PER_SECOND as f32).floor().max(0.0) as usize;
+        let last = ((end * PEAKS_PER_SECOND as f32).ceil() as usize).max(first + 1).min(self.peaks.len());
+        self.peaks.get(first..last)?.iter().copied().reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+    }
+}
+
+// Starts loading the peaks for `path`. The receiver gets None if the video has no audio.
+pub fn load(ctx: &egui::Context, path: &str) -> Receiver<Option<Peaks>> {
+    let (sender, receiver) = channel();
+    let path = path.to_owned();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let cache_path = cache::cache_file(&path, "peaks");
+        let cached = cache_path.as_ref().and_then(|p| fs::read(p).ok());
+        let peaks = match cached {
+            Some(bytes) => Some(Peaks::from_bytes(&bytes)),
+            None => {
+                let peaks = decode_peaks(&path);
+                if let (Some(peaks), Some(cache_path)) = (&peaks, &cache_path) {
+                    let _ = fs::write(cache_path, peaks.to_bytes());
+                }
+                peaks
+            }
+        };
+        let _ = sender.send(peaks);
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+fn decode_peaks(path: &str) -> Option<Peaks> {
+    let sample_rate = SAMPLE_RATE.to_string();
+    let mut child = hidden_command("ffmpeg")
+        .args(["-v", "error", "-i", path, "-vn", "-ac", "1", "-ar", &sample_rate, "-f", "s16le", "pipe:1"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    let mut buffer = [0u8; SAMPLES_PER_PEAK * 2];
+    let mut peaks = vec![];
+    loop {
+        // The last chunk is usually short, so read_exact can't be used here.
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match stdout.read(&mut buffer[filled..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => filled += n,
+            }
+        }
+        if filled < 2 {
+            break;
+        }
+        let (min, max) = buffer[..filled]
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / i16::MAX as f32)
+            .fold((0.0f32, 0.0f32), |(min, max), s| (min.min(s), max.max(s)));
+        peaks.push([min, max]);
+    }
+    let _ = child.wait();
+    if peaks.is_empty() {
+        None
+    } else {
+        Some(Peaks { peaks })
+    }
+}
+
+// Draws the waveform for the `visible` time range across the full width of the lane.
+pub fn waveform_lane(ui: &mut egui::Ui, peaks: Option<&Peaks>, loading: bool, visible: RangeInclusive<f32>, width: f32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 40.0), egui::Sense::hover());
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_hex("#252525").unwrap());
+
+    let Some(peaks) = peaks else {
+        let text = if loading { "Loading waveform..." } else { "No audio" };
+        painter.text(rect.center(), egui::Align2::CENTER_CENTER, text, egui::FontId::default(), Color32::GRAY);
+        return response;
+    };
+    let (start, end) = (*visible.start(), *visible.end());
+    let seconds_per_pixel = (end - start) / rect.width();
+    let half_height = rect.height() / 2.0;
+    let mut silence_from = None;
+    for column in 0..rect.width() as usize {
+        let x = rect.left() + column as f32;
+        let t = start + column as f32 * seconds_per_pixel;
+        let Some([min, max]) = peaks.range(t, t + seconds_per_pixel) else {
+            continue;
+        };
+        // Quiet stretches are collected into one shaded rect instead of a rect per column.
+        let silent = max.max(-min) < SILENCE_THRESHOLD;
+        match (silent, silence_from) {
+            (true, None) => silence_from = Some(x),
+            (false, Some(from)) => {
+                paint_silence(&painter, rect, from, x);
+                silence_from = None;
+            }
+            _ => {}
+        }
+        painter.vline(
+            x,
+            (rect.center().y - max * half_height)..=(rect.center().y - min * half_height),
+            egui::Stroke::new(1.0, Color32::from_hex("#8fd18f").unwrap()),
+        );
+    }
+    if let Some(from) = silence_from {
+        paint_silence(&painter, rect, from, rect.right());
+    }
+    response
+}
+
+fn paint_silence(painter: &egui::Painter, lane: egui::Rect, from: f32, to: f32) {
+    let rect = egui::Rect::from_x_y_ranges(from..=to, lane.y_range());
+    painter.rect_filled(rect, 0.0, Color32::from_rgba_unmultiplied(90, 140, 255, 40));
+}